@@ -0,0 +1,476 @@
+//! Analysis of the instructions of a [`Dockerfile`]
+
+use std::ops::Range;
+
+use crate::{instruction::Instruction, Dockerfile};
+
+/// Build argument declared by an `ARG` instruction
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ArgSpec {
+    pub name: String,
+    pub default: Option<String>,
+    /// Allowed values of an `ARG` added with [`Dockerfile::push_arg`]
+    pub choices: Option<Vec<String>>,
+}
+
+/// Overview of the structure of a [`Dockerfile`], see [`Dockerfile::summary`]
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct DockerfileSummary {
+    /// Number of build stages, i.e. `FROM` instructions
+    pub stages: usize,
+    /// Number of `RUN` instructions
+    pub runs: usize,
+    /// Number of `COPY` and `ADD` instructions
+    pub copies: usize,
+    /// Number of layers added by the final stage, see [`Dockerfile::estimated_layers`]
+    pub layers: usize,
+    /// Number of distinct build arguments, see [`Dockerfile::build_args`]
+    pub args: usize,
+}
+
+impl Dockerfile {
+    /// Returns an overview of the structure of the Dockerfile
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::analysis::DockerfileSummary;
+    /// # use dockerfile_builder::instruction::{ARG, COPY, FROM, RUN};
+    /// let dockerfile = Dockerfile::default()
+    ///     .push(ARG::from("VERSION"))
+    ///     .push(FROM::from("rust:${VERSION} AS builder"))
+    ///     .push(RUN::from("cargo build"))
+    ///     .push(FROM::from("debian"))
+    ///     .push(COPY::from("--from=builder /app /app"));
+    /// assert_eq!(
+    ///     dockerfile.summary(),
+    ///     DockerfileSummary { stages: 2, runs: 1, copies: 1, layers: 1, args: 1 },
+    /// );
+    /// ```
+    pub fn summary(&self) -> DockerfileSummary {
+        let count = |f: fn(&Instruction) -> bool| self.instructions.iter().filter(|i| f(i)).count();
+        DockerfileSummary {
+            stages: count(|i| matches!(i, Instruction::FROM(_))),
+            runs: count(|i| matches!(i, Instruction::RUN(_))),
+            copies: count(|i| matches!(i, Instruction::COPY(_) | Instruction::ADD(_))),
+            layers: self.estimated_layers(),
+            args: self.build_args().len(),
+        }
+    }
+
+    /// Returns the build arguments declared by `ARG` instructions, in declaration order
+    ///
+    /// An argument declared several times, e.g. globally and again inside a stage, is returned
+    /// once, with the first default value and choices found.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::analysis::ArgSpec;
+    /// # use dockerfile_builder::instruction::{ARG, FROM};
+    /// let dockerfile = Dockerfile::default()
+    ///     .push(ARG::from("VERSION=1.70"))
+    ///     .push(FROM::from("rust:${VERSION}"))
+    ///     .push(ARG::from("PROFILE"));
+    ///
+    /// assert_eq!(
+    ///     dockerfile.build_args(),
+    ///     vec![
+    ///         ArgSpec {
+    ///             name: "VERSION".to_string(),
+    ///             default: Some("1.70".to_string()),
+    ///             choices: None,
+    ///         },
+    ///         ArgSpec { name: "PROFILE".to_string(), default: None, choices: None },
+    ///     ],
+    /// );
+    /// ```
+    pub fn build_args(&self) -> Vec<ArgSpec> {
+        let mut args: Vec<ArgSpec> = vec![];
+        for instruction in &self.instructions {
+            let Instruction::ARG(arg) = instruction else {
+                continue;
+            };
+            for mut spec in parse_arg(&arg.value) {
+                spec.choices = self.arg_choices.get(&spec.name).cloned();
+                match args.iter_mut().find(|a| a.name == spec.name) {
+                    Some(existing) => {
+                        if existing.default.is_none() {
+                            existing.default = spec.default;
+                        }
+                        if existing.choices.is_none() {
+                            existing.choices = spec.choices;
+                        }
+                    }
+                    None => args.push(spec),
+                }
+            }
+        }
+        args
+    }
+
+    /// Returns the shell used by shell form instructions at the end of the Dockerfile
+    ///
+    /// This is the exec array of the last `SHELL` of the final build stage, or the default
+    /// `["/bin/sh", "-c"]` of Linux images if the stage doesn't set one. Pass it to
+    /// [`RunBuilder::exec_shell`](crate::instruction_builder::RunBuilderInner::exec_shell) to
+    /// render a `RUN` independent of the active shell.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::{FROM, SHELL};
+    /// let dockerfile = Dockerfile::default().push(FROM::from("alpine"));
+    /// assert_eq!(dockerfile.active_shell(), vec!["/bin/sh", "-c"]);
+    ///
+    /// let dockerfile = dockerfile.push(SHELL::from(r#"["/bin/bash", "-o", "pipefail", "-c"]"#));
+    /// assert_eq!(dockerfile.active_shell(), vec!["/bin/bash", "-o", "pipefail", "-c"]);
+    /// ```
+    pub fn active_shell(&self) -> Vec<String> {
+        let final_stage = stage_ranges(&self.instructions).pop().unwrap_or_default();
+        self.instructions[final_stage]
+            .iter()
+            .rev()
+            .find_map(|i| match i {
+                Instruction::SHELL(shell) => exec_form(&shell.value),
+                _ => None,
+            })
+            .unwrap_or_else(|| vec!["/bin/sh".to_string(), "-c".to_string()])
+    }
+
+    /// Returns the number of filesystem layers added by the final build stage
+    ///
+    /// Every `RUN`, `COPY` and `ADD` instruction after the last `FROM` adds a layer. Layers of
+    /// the base image aren't counted.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::{COPY, ENV, FROM, RUN};
+    /// let dockerfile = Dockerfile::default()
+    ///     .push(FROM::from("rust AS builder"))
+    ///     .push(RUN::from("cargo build --release"))
+    ///     .push(FROM::from("debian"))
+    ///     .push(ENV::from("RUST_LOG=info"))
+    ///     .push(COPY::from("--from=builder /app /app"))
+    ///     .push(RUN::from("/app --version"));
+    /// assert_eq!(dockerfile.estimated_layers(), 2);
+    /// ```
+    pub fn estimated_layers(&self) -> usize {
+        let final_stage = self
+            .instructions
+            .iter()
+            .rposition(|i| matches!(i, Instruction::FROM(_)))
+            .map_or(0, |index| index + 1);
+        self.instructions[final_stage..]
+            .iter()
+            .filter(|i| {
+                matches!(
+                    i,
+                    Instruction::RUN(_) | Instruction::COPY(_) | Instruction::ADD(_)
+                )
+            })
+            .count()
+    }
+}
+
+/// Parses an `ARG <name>[=<default>] ...` value
+fn parse_arg(value: &str) -> Vec<ArgSpec> {
+    split_words(value)
+        .into_iter()
+        .map(|word| match word.split_once('=') {
+            Some((name, default)) => ArgSpec {
+                name: name.to_string(),
+                default: Some(unquote(default)),
+                choices: None,
+            },
+            None => ArgSpec {
+                name: word,
+                default: None,
+                choices: None,
+            },
+        })
+        .collect()
+}
+
+/// Splits an instruction value on whitespace, keeping quoted sections and escaped characters
+/// together
+pub(crate) fn split_words(value: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut quote: Option<char> = None;
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if quote != Some('\'') => {
+                word.push(c);
+                if let Some(next) = chars.next() {
+                    word.push(next);
+                }
+            }
+            '"' | '\'' if quote.is_none() => {
+                quote = Some(c);
+                word.push(c);
+            }
+            c if quote == Some(c) => {
+                quote = None;
+                word.push(c);
+            }
+            c if c.is_whitespace() && quote.is_none() => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Returns the index ranges of the build stages, each starting with its `FROM`
+///
+/// Instructions before the first `FROM`, if any, are returned as their own range.
+pub(crate) fn stage_ranges(instructions: &[Instruction]) -> Vec<Range<usize>> {
+    let mut starts = instructions
+        .iter()
+        .enumerate()
+        .filter(|(_, i)| matches!(i, Instruction::FROM(_)))
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    if starts.first() != Some(&0) && !instructions.is_empty() {
+        starts.insert(0, 0);
+    }
+    let ends = starts.iter().skip(1).copied().chain([instructions.len()]);
+    starts
+        .iter()
+        .zip(ends)
+        .map(|(&start, end)| start..end)
+        .collect()
+}
+
+/// Parses the JSON array of an exec form instruction value, e.g. `["echo", "hi"]`
+///
+/// Returns `None` if the value isn't a JSON array of strings, i.e. is in shell form.
+pub(crate) fn exec_form(value: &str) -> Option<Vec<String>> {
+    let inner = value.trim().strip_prefix('[')?.strip_suffix(']')?;
+    let mut words = vec![];
+    let mut chars = inner.trim().chars().peekable();
+    if chars.peek().is_none() {
+        return Some(words);
+    }
+    loop {
+        if chars.next()? != '"' {
+            return None;
+        }
+        let mut word = String::new();
+        loop {
+            match chars.next()? {
+                '"' => break,
+                '\\' => match chars.next()? {
+                    'n' => word.push('\n'),
+                    't' => word.push('\t'),
+                    'r' => word.push('\r'),
+                    'b' => word.push('\u{8}'),
+                    'f' => word.push('\u{c}'),
+                    'u' => {
+                        let hex = chars.by_ref().take(4).collect::<String>();
+                        word.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                    }
+                    c => word.push(c),
+                },
+                c => word.push(c),
+            }
+        }
+        words.push(word);
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        match chars.next() {
+            None => return Some(words),
+            Some(',') => while chars.next_if(|c| c.is_whitespace()).is_some() {},
+            Some(_) => return None,
+        }
+    }
+}
+
+/// Removes the double quotes around a value and unescapes it
+fn unquote(value: &str) -> String {
+    match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(inner) => inner.replace(r#"\""#, r#"""#).replace(r"\\", r"\"),
+        None => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction::{ADD, ARG, FROM, RUN, SHELL, WORKDIR};
+    use crate::instruction_builder::{ArgBuilder, BuilderError, RunBuilder};
+
+    #[test]
+    fn build_args() {
+        let dockerfile = Dockerfile::default()
+            .push(ARG::from("BASE"))
+            .push(FROM::from("${BASE}"))
+            .push(ARG::from(r#"GREETING="hello world""#))
+            .push(
+                ArgBuilder::builder()
+                    .name("BASE")
+                    .value("alpine")
+                    .build()
+                    .unwrap(),
+            );
+
+        assert_eq!(
+            dockerfile.build_args(),
+            vec![
+                ArgSpec {
+                    name: "BASE".to_string(),
+                    default: Some("alpine".to_string()),
+                    choices: None,
+                },
+                ArgSpec {
+                    name: "GREETING".to_string(),
+                    default: Some("hello world".to_string()),
+                    choices: None,
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn build_args_choices() {
+        let dockerfile = Dockerfile::default()
+            .push_arg(
+                ArgBuilder::builder()
+                    .name("BUILD_MODE")
+                    .value("release")
+                    .choices(vec!["debug", "release"]),
+            )
+            .unwrap();
+        let expected = Some(vec!["debug".to_string(), "release".to_string()]);
+        assert_eq!(dockerfile.to_string(), "ARG BUILD_MODE=release");
+        assert_eq!(dockerfile.build_args()[0].choices, expected);
+        assert_eq!(
+            dockerfile.clone().strip_comments().build_args()[0].choices,
+            expected
+        );
+
+        let built = ArgBuilder::builder()
+            .name("BUILD_MODE")
+            .value("release")
+            .choices(vec!["debug", "release"])
+            .build()
+            .unwrap();
+        assert_eq!(built, ARG::from("BUILD_MODE=release"));
+
+        let merged = Dockerfile::default() + dockerfile;
+        assert_eq!(merged.build_args()[0].choices, expected);
+
+        let err = Dockerfile::default()
+            .push_arg(
+                ArgBuilder::builder()
+                    .name("BUILD_MODE")
+                    .value("fast")
+                    .choices(vec!["debug", "release"]),
+            )
+            .unwrap_err();
+        assert!(matches!(err, BuilderError::Invalid(_)));
+
+        let parsed: Dockerfile = "# choices: a|b\nARG BUILD_MODE".parse().unwrap();
+        assert_eq!(parsed.build_args()[0].choices, None);
+    }
+
+    #[test]
+    fn active_shell() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("debian AS builder"))
+            .push(SHELL::from(r#"["/bin/bash", "-c"]"#));
+        let run = RunBuilder::builder()
+            .exec_shell(dockerfile.active_shell())
+            .command("echo $0")
+            .build()
+            .unwrap();
+        assert_eq!(run.to_string(), r#"RUN ["/bin/bash", "-c", "echo $0"]"#);
+
+        let dockerfile = dockerfile.push(FROM::from("debian"));
+        assert_eq!(dockerfile.active_shell(), vec!["/bin/sh", "-c"]);
+    }
+
+    #[test]
+    fn estimated_layers() {
+        let dockerfile = Dockerfile::default()
+            .push(RUN::from("echo before from"))
+            .push(FROM::from("alpine"))
+            .push(RUN::from("apk add curl"))
+            .push(ADD::from("app.tar.gz /app"))
+            .push(WORKDIR::from("/app"));
+        assert_eq!(dockerfile.estimated_layers(), 2);
+        assert_eq!(Dockerfile::default().estimated_layers(), 0);
+    }
+
+    #[test]
+    fn summary() {
+        let dockerfile = Dockerfile::default()
+            .push(ARG::from("BASE=alpine"))
+            .push(FROM::from("${BASE} AS deps"))
+            .push(ARG::from("BASE"))
+            .push(RUN::from("apk add curl"))
+            .push(FROM::from("deps"))
+            .push(ADD::from("app.tar.gz /app"))
+            .push(RUN::from("make"))
+            .push(RUN::from("make install"));
+        assert_eq!(
+            dockerfile.summary(),
+            DockerfileSummary {
+                stages: 2,
+                runs: 3,
+                copies: 1,
+                layers: 3,
+                args: 1,
+            },
+        );
+        assert_eq!(
+            Dockerfile::default().summary(),
+            DockerfileSummary::default()
+        );
+    }
+
+    #[test]
+    fn stage_ranges() {
+        let instructions = vec![
+            Instruction::from(ARG::from("BASE")),
+            Instruction::from(FROM::from("alpine")),
+            Instruction::from(RUN::from("make")),
+            Instruction::from(FROM::from("scratch")),
+        ];
+        assert_eq!(super::stage_ranges(&instructions), vec![0..1, 1..3, 3..4]);
+        assert_eq!(super::stage_ranges(&instructions[1..]), vec![0..2, 2..3]);
+        assert!(super::stage_ranges(&[]).is_empty());
+    }
+
+    #[test]
+    fn exec_form() {
+        assert_eq!(
+            super::exec_form(r#"[ "echo", "a \"b\"\n" ,"A" ]"#),
+            Some(vec![
+                "echo".to_string(),
+                "a \"b\"\n".to_string(),
+                "A".to_string()
+            ]),
+        );
+        assert_eq!(super::exec_form("[]"), Some(vec![]));
+        assert_eq!(super::exec_form("echo [a]"), None);
+        assert_eq!(super::exec_form(r#"["echo" "hi"]"#), None);
+        assert_eq!(super::exec_form(r#"["echo", hi]"#), None);
+    }
+
+    #[test]
+    fn words() {
+        assert_eq!(
+            split_words(r#"A=1  B="x \" y" C"#),
+            vec!["A=1", r#"B="x \" y""#, "C"],
+        );
+        assert_eq!(
+            split_words(r#"echo 'a  "b' My\ Dog"#),
+            vec!["echo", r#"'a  "b'"#, r"My\ Dog"],
+        );
+    }
+}
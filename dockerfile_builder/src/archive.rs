@@ -0,0 +1,81 @@
+//! Tar archive helpers for [`Dockerfile`], enabled by the `tar` feature
+
+use std::io::{self, Write};
+
+use crate::Dockerfile;
+
+/// Conventional path of the Dockerfile inside a build context
+pub const DOCKERFILE_PATH: &str = "Dockerfile";
+
+impl Dockerfile {
+    /// Renders the Dockerfile as a tar entry at [`DOCKERFILE_PATH`]
+    ///
+    /// Returns the entry header and its content. The content ends with a newline, like the file
+    /// written by [`Dockerfile::write_to_file`]. The header has mode `0644` and a zero mtime so
+    /// the generated archive is reproducible.
+    pub fn to_tar_entry(&self) -> io::Result<(tar::Header, Vec<u8>)> {
+        let mut content = Vec::new();
+        self.to_writer(&mut content)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_path(DOCKERFILE_PATH)?;
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_cksum();
+        Ok((header, content))
+    }
+
+    /// Appends the rendered Dockerfile to a [`tar::Builder`] at [`DOCKERFILE_PATH`]
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::FROM;
+    /// let dockerfile = Dockerfile::default().push(FROM::from("alpine"));
+    /// let mut archive = tar::Builder::new(Vec::new());
+    /// dockerfile.append_to_tar(&mut archive).unwrap();
+    /// let bytes = archive.into_inner().unwrap();
+    /// ```
+    pub fn append_to_tar<W: Write>(&self, builder: &mut tar::Builder<W>) -> io::Result<()> {
+        let (header, content) = self.to_tar_entry()?;
+        builder.append(&header, content.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::instruction::{FROM, RUN};
+
+    #[test]
+    fn tar_entry() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .push(RUN::from("echo $HOME"));
+
+        let (header, content) = dockerfile.to_tar_entry().unwrap();
+        assert_eq!(header.path().unwrap().to_str(), Some("Dockerfile"));
+        assert_eq!(header.size().unwrap(), content.len() as u64);
+        assert_eq!(content, b"FROM alpine\nRUN echo $HOME\n");
+    }
+
+    #[test]
+    fn append_to_tar() {
+        let dockerfile = Dockerfile::default().push(FROM::from("alpine"));
+        let mut builder = tar::Builder::new(Vec::new());
+        dockerfile.append_to_tar(&mut builder).unwrap();
+        let bytes = builder.into_inner().unwrap();
+
+        let mut archive = tar::Archive::new(bytes.as_slice());
+        let mut entries = archive.entries().unwrap();
+        let mut entry = entries.next().unwrap().unwrap();
+        assert_eq!(entry.path().unwrap().to_str(), Some("Dockerfile"));
+
+        let mut content = String::new();
+        entry.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "FROM alpine\n");
+        assert!(entries.next().is_none());
+    }
+}
@@ -4,6 +4,8 @@
 
 use dockerfile_builder_macros::InstructionInit;
 
+use crate::analysis::split_words;
+
 /// Dockerfile Instructions
 ///
 /// There are 2 ways to build an Instruction:
@@ -26,8 +28,14 @@ use dockerfile_builder_macros::InstructionInit;
 ///
 /// assert_eq!(from, from_by_builder);
 /// ```
+///
+/// Every instruction struct, e.g. [`FROM`], has a public `comment` field holding the comment
+/// rendered on the lines before it, see the `comment()` setter of the builders.
+///
+/// `==` compares the comments too, use [`Instruction::semantic_eq`] to ignore them.
 //#[derive(Debug, Clone, Eq, PartialEq)]
 #[derive(Debug, InstructionInit, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Instruction {
     FROM(FROM),
     ENV(ENV),
@@ -46,6 +54,16 @@ pub enum Instruction {
     STOPSIGNAL(STOPSIGNAL),
     HEALTHCHECK(HEALTHCHECK),
     SHELL(SHELL),
+    /// Deprecated by Docker, kept to model legacy Dockerfiles. New Dockerfiles should use
+    /// `LABEL org.opencontainers.image.authors=<name>` instead, see
+    /// [`Dockerfile::maintainer`](crate::Dockerfile::maintainer)
+    MAINTAINER(MAINTAINER),
+    /// Comment text without the leading `#`, each line is rendered as `# <line>`
+    ///
+    /// A standalone comment, unlike the `comment` field of the instruction structs which is
+    /// attached to and rendered before its instruction. Both render the same way, so parsing
+    /// them back gives one `COMMENT` per line, with trailing whitespace trimmed.
+    COMMENT(String),
     ANY(String),
 }
 
@@ -57,3 +75,129 @@ where
         Instruction::ANY(instruction.into())
     }
 }
+
+impl Instruction {
+    /// Returns an equivalent instruction in canonical form
+    ///
+    /// * Runs of whitespace outside quotes are collapsed into a single space.
+    /// * Leading flags of `FROM`, `RUN`, `ADD`, `COPY` and `HEALTHCHECK` are sorted by name.
+    /// * Multi-line values, e.g. heredocs, are only trimmed at the end of each line.
+    /// * The `comment` of the instruction is removed.
+    ///
+    /// ```
+    /// # use dockerfile_builder::instruction::{Instruction, COPY, RUN};
+    /// let run = Instruction::from(RUN::from("  echo   hi "));
+    /// assert_eq!(run.canonical().to_string(), "RUN echo hi");
+    ///
+    /// let copy = Instruction::from(COPY::from("--link --chown=1000 src/  /app/"));
+    /// assert_eq!(copy.canonical().to_string(), "COPY --chown=1000 --link src/ /app/");
+    /// ```
+    pub fn canonical(&self) -> Instruction {
+        let sort_flags = matches!(
+            self,
+            Instruction::FROM(_)
+                | Instruction::RUN(_)
+                | Instruction::ADD(_)
+                | Instruction::COPY(_)
+                | Instruction::HEALTHCHECK(_)
+        );
+        let mut canonical = self.clone();
+        if let Some(comment) = canonical.comment_mut() {
+            *comment = None;
+        }
+        let value = canonical.value_mut();
+        *value = if value.contains('\n') {
+            value
+                .lines()
+                .map(|line| line.trim_end())
+                .collect::<Vec<_>>()
+                .join("\n")
+                .trim()
+                .to_string()
+        } else if let Instruction::ANY(_) | Instruction::COMMENT(_) = self {
+            value.trim().to_string()
+        } else {
+            canonical_words(value, sort_flags)
+        };
+        canonical
+    }
+
+    /// Returns `true` if both instructions have the same [canonical](Instruction::canonical) form
+    pub fn semantic_eq(&self, other: &Instruction) -> bool {
+        self.canonical() == other.canonical()
+    }
+}
+
+fn canonical_words(value: &str, sort_flags: bool) -> String {
+    let mut words = split_words(value);
+    if sort_flags {
+        let flag_count = words.iter().take_while(|w| w.starts_with("--")).count();
+        words[..flag_count].sort_by(|a, b| flag_name(a).cmp(flag_name(b)));
+    }
+    words.join(" ")
+}
+
+fn flag_name(flag: &str) -> &str {
+    flag.split('=').next().unwrap_or(flag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical() {
+        let run = Instruction::from(RUN::from("echo   hi"));
+        assert_eq!(run.canonical(), Instruction::from(RUN::from("echo hi")));
+        assert!(run.semantic_eq(&Instruction::from(RUN::from(" echo hi  "))));
+
+        let run = Instruction::from(RUN::from(r#"echo "a   b"  'c   d'"#));
+        assert_eq!(run.canonical().to_string(), r#"RUN echo "a   b" 'c   d'"#);
+
+        let from = Instruction::from(FROM::from("rust  AS  builder"));
+        assert_eq!(from.canonical().to_string(), "FROM rust AS builder");
+
+        let cmd = Instruction::from(CMD::from("--b --a"));
+        assert_eq!(cmd.canonical().to_string(), "CMD --b --a");
+
+        let any = Instruction::from("  # comment  ");
+        assert_eq!(any.canonical(), Instruction::from("# comment"));
+
+        let comment = Instruction::COMMENT(" a  comment ".to_string());
+        assert_eq!(comment.canonical().to_string(), "# a  comment");
+    }
+
+    #[test]
+    fn semantic_eq_ignores_comment() {
+        let from = FROM::from("alpine");
+        let commented = FROM {
+            comment: Some("base image".to_string()),
+            ..from.clone()
+        };
+        assert_ne!(
+            Instruction::from(from.clone()),
+            Instruction::from(commented.clone())
+        );
+        assert!(Instruction::from(from).semantic_eq(&Instruction::from(commented)));
+    }
+
+    #[test]
+    fn keyword() {
+        let keywords = [
+            Instruction::from(FROM::from("alpine")),
+            Instruction::from(MAINTAINER::from("me")),
+            Instruction::COMMENT("install".to_string()),
+            Instruction::from("RUN echo raw"),
+        ]
+        .iter()
+        .map(Instruction::keyword)
+        .collect::<Vec<_>>();
+        assert_eq!(keywords, vec![Some("FROM"), Some("MAINTAINER"), None, None]);
+    }
+
+    #[test]
+    fn comment() {
+        let comment = Instruction::COMMENT("first\n\nthird ".to_string());
+        assert_eq!(comment.to_string(), "# first\n#\n# third");
+    }
+}
@@ -15,19 +15,20 @@
 //! `ExposeBuilder` is the builder struct for `Expose`.
 //!
 //! ```rust
+//! # use dockerfile_builder::instruction_builder::Protocol;
 //! pub struct ExposeBuilder {
-//!     pub port: u16,
-//!     pub protocol: Option<String>,
+//!     pub port: Option<u16>,
+//!     pub protocol: Option<Protocol>,
 //! }
 //! ```
 //!
 //! `Expose` can be constructed as follow:
 //!
 //! ```rust
-//! # use dockerfile_builder::instruction_builder::ExposeBuilder;
+//! # use dockerfile_builder::instruction_builder::{ExposeBuilder, Protocol};
 //! let expose = ExposeBuilder::builder()
 //!     .port(80)
-//!     .protocol("tcp")
+//!     .protocol(Protocol::Tcp)
 //!     .build()
 //!     .unwrap();
 //! ```
@@ -35,9 +36,31 @@
 //! Note that:
 //! * The setter method names are identical to the fields names.
 //! * For fields with `Option<inner_type>` type: The argument type is the inner_type. It is
-//! optional to set these fields.
-//! * Use `build()` to complete building the instruction. `build()` returns
-//! `Result<InstructionBuilder, std::err::Err>` to safely handle errors.
+//!   optional to set these fields.
+//! * Some fields have a default value, e.g. the `delimiter` of `RunHeredocBuilder`. It is
+//!   optional to set these fields, the default is used if they are unset.
+//! * Every builder has a `comment()` setter. The comment is rendered on the lines before the
+//!   instruction.
+//! * Use `build()` to complete building the instruction. `build()` returns a
+//!   [`BuildResult`] to safely handle errors.
+//!
+//! [`BuildResult`] only uses types from this crate, so errors can be propagated with `?` without
+//! extra dependencies:
+//!
+//! ```
+//! use dockerfile_builder::instruction_builder::{
+//!     BuildResult, BuilderError, ExposeBuilder, FromBuilder,
+//! };
+//!
+//! fn expose(port: u16) -> BuildResult<String> {
+//!     let expose = ExposeBuilder::builder().port(port).build()?;
+//!     Ok(expose.to_string())
+//! }
+//!
+//! assert_eq!(expose(80).unwrap(), "EXPOSE 80");
+//! let err: BuilderError = FromBuilder::builder().build().unwrap_err();
+//! assert!(matches!(err, BuilderError::MissingField { field: "image", .. }));
+//! ```
 //!
 //!
 //! For fields with `Vec<_>` or `Option<Vec<_>>` type, it is possible to set each element of the Vec.
@@ -68,22 +91,62 @@
 //!
 
 use crate::instruction::{
-    Instruction, ADD, ARG, CMD, COPY, ENTRYPOINT, ENV, EXPOSE, FROM, HEALTHCHECK, LABEL, ONBUILD,
-    RUN, SHELL, STOPSIGNAL, USER, VOLUME, WORKDIR,
+    Instruction, ADD, ARG, CMD, COPY, ENTRYPOINT, ENV, EXPOSE, FROM, HEALTHCHECK, LABEL,
+    MAINTAINER, ONBUILD, RUN, SHELL, STOPSIGNAL, USER, VOLUME, WORKDIR,
 };
 use dockerfile_builder_macros::InstructionBuilder;
-use eyre::{eyre, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Errors returned by the `build()` method of the instruction builders
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum BuilderError {
+    /// A required field isn't set
+    MissingField {
+        field: &'static str,
+        builder: &'static str,
+    },
+    /// The fields don't make a valid instruction
+    Invalid(String),
+}
+
+impl Display for BuilderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuilderError::MissingField { field, builder } => {
+                write!(f, "{} is required for {}", field, builder)
+            }
+            BuilderError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for BuilderError {}
+
+/// Result of the `build()` method of the instruction builders
+pub type BuildResult<T> = Result<T, BuilderError>;
+
+/// Creates a [`BuilderError::Invalid`] from a format string
+macro_rules! invalid {
+    ($($arg:tt)*) => {
+        BuilderError::Invalid(format!($($arg)*))
+    };
+}
 
 /// Builder struct for [`FROM`] instruction
 ///
 /// Format according to [Dockerfile
 /// reference](https://docs.docker.com/engine/reference/builder/#from):
 /// * `FROM [--platform=<platform>] <image> [AS <name>]`
-/// or
+///   or
 /// * `FROM [--platform=<platform>] <image>[:<tag>] [AS <name>]`
-/// or
+///   or
 /// * `FROM [--platform=<platform>] <image>[@<digest>] [AS <name>]`
 ///
+/// `stage` marks `image` as a previous build stage, see [`FromBuilder::from_stage`].
+///
 /// Example:
 /// ```
 /// # use dockerfile_builder::instruction_builder::FromBuilder;
@@ -103,6 +166,22 @@ use eyre::{eyre, Result};
 ///     .build()
 ///     .unwrap();
 /// assert_eq!(from.to_string(), "FROM cargo-chef:latest AS chef");
+///
+/// // Build FROM with a stage header comment
+/// let from = FromBuilder::builder()
+///     .comment("---- builder stage ----")
+///     .image("rust")
+///     .name("builder")
+///     .build()
+///     .unwrap();
+/// assert_eq!(from.to_string(), "# ---- builder stage ----\nFROM rust AS builder");
+///
+/// // Build FROM a previous build stage
+/// let from = FromBuilder::from_stage("builder")
+///     .name("tester")
+///     .build()
+///     .unwrap();
+/// assert_eq!(from.to_string(), "FROM builder AS tester");
 /// ```
 ///
 /// [FROM]: dockerfile_builder::instruction::FROM
@@ -116,13 +195,33 @@ pub struct FromBuilder {
     pub name: Option<String>,
     pub tag: Option<String>,
     pub digest: Option<String>,
-    pub platform: Option<String>,
+    pub platform: Option<Platform>,
+    pub stage: Option<bool>,
 }
 
 impl FromBuilder {
-    fn value(&self) -> Result<String> {
+    /// Starts building a `FROM` a previous build stage
+    ///
+    /// The stage already has a platform, tag and digest, setting them fails the build.
+    pub fn from_stage<T: Into<String>>(stage: T) -> FromBuilderInner {
+        let mut builder = FromBuilder::builder();
+        builder.image(stage).stage(true);
+        builder
+    }
+
+    fn value(&self) -> BuildResult<String> {
         if self.tag.is_some() && self.digest.is_some() {
-            return Err(eyre!("Dockerfile image can only have tag OR digest"));
+            return Err(invalid!("Dockerfile image can only have tag OR digest"));
+        }
+        if self.stage == Some(true) {
+            if self.platform.is_some() {
+                return Err(invalid!(
+                    "FROM --platform cannot be used with a build stage"
+                ));
+            }
+            if self.tag.is_some() || self.digest.is_some() {
+                return Err(invalid!("FROM a build stage cannot have a tag or digest"));
+            }
         }
 
         let tag_or_digest = if let Some(t) = &self.tag {
@@ -150,11 +249,99 @@ impl FromBuilder {
     }
 }
 
+/// Target platform for the `FROM --platform` flag of [`FromBuilder`]
+///
+/// Use [`str::parse`] to check the `os/arch[/variant]` shape of any other platform, or
+/// [`Platform::Custom`] to skip the check.
+///
+/// Example:
+/// ```
+/// # use dockerfile_builder::instruction_builder::{FromBuilder, Platform};
+/// let from = FromBuilder::builder()
+///     .image("rust")
+///     .platform(Platform::LinuxArm64)
+///     .build().unwrap();
+/// assert_eq!(from.to_string(), "FROM --platform=linux/arm64 rust");
+///
+/// let platform: Platform = "linux/ppc64le".parse().unwrap();
+/// assert_eq!(platform, Platform::Custom("linux/ppc64le".to_string()));
+/// assert_eq!("linux/arm/v7".parse::<Platform>().unwrap(), Platform::LinuxArmV7);
+/// assert!("linux/amdd64/v1/v2".parse::<Platform>().is_err());
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Platform {
+    /// `linux/amd64`
+    LinuxAmd64,
+    /// `linux/arm64`
+    LinuxArm64,
+    /// `linux/arm/v7`
+    LinuxArmV7,
+    /// `windows/amd64`
+    WindowsAmd64,
+    /// Any other platform, e.g. `linux/s390x` or `$BUILDPLATFORM`
+    Custom(String),
+}
+
+impl Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let platform = match self {
+            Platform::LinuxAmd64 => "linux/amd64",
+            Platform::LinuxArm64 => "linux/arm64",
+            Platform::LinuxArmV7 => "linux/arm/v7",
+            Platform::WindowsAmd64 => "windows/amd64",
+            Platform::Custom(platform) => platform,
+        };
+        write!(f, "{}", platform)
+    }
+}
+
+impl FromStr for Platform {
+    type Err = BuilderError;
+
+    /// Parses an `os/arch[/variant]` platform or a `$VARIABLE` reference
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let known = [
+            Platform::LinuxAmd64,
+            Platform::LinuxArm64,
+            Platform::LinuxArmV7,
+            Platform::WindowsAmd64,
+        ];
+        if let Some(platform) = known.into_iter().find(|p| p.to_string() == s) {
+            return Ok(platform);
+        }
+        let is_variable = s.starts_with('$');
+        let segments = s.split('/').collect::<Vec<_>>();
+        let is_platform = (2..=3).contains(&segments.len())
+            && segments.iter().all(|segment| {
+                !segment.is_empty()
+                    && segment
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+            });
+        if !is_variable && !is_platform {
+            return Err(invalid!(
+                "platform must have the form os/arch[/variant], got {}",
+                s
+            ));
+        }
+        Ok(Platform::Custom(s.to_string()))
+    }
+}
+
 /// Builder struct for [`ENV`] instruction
 ///
 /// Format according to [Dockerfile
 /// reference](https://docs.docker.com/engine/reference/builder/#env):
-/// * `ENV <key>=<value>`
+/// * `ENV <key>=<value> ...`
+///   or, set with `legacy_form`
+/// * `ENV <key> <value>`
+///
+/// Values containing whitespace, `=`, quotes or backslashes are wrapped in double quotes. Empty
+/// values are rendered as `KEY=""`, set `bare_empty` to render them as `KEY=`. Set
+/// `normalize_name` to uppercase the keys.
+///
+/// The legacy form sets a single variable to the rest of the line, as is. It's only useful to
+/// match existing Dockerfiles, the `=` form is preferred.
 ///
 /// Example:
 /// ```
@@ -165,6 +352,56 @@ impl FromBuilder {
 ///     .build()
 ///     .unwrap();
 /// assert_eq!(env.to_string(), "ENV foo=bar");
+///
+/// // values with newlines are quoted and escaped
+/// let env = EnvBuilder::builder()
+///     .key("CONFIG")
+///     .value("line1\nline2")
+///     .build()
+///     .unwrap();
+/// assert_eq!(env.to_string(), r#"ENV CONFIG="line1\nline2""#);
+///
+/// // build ENV with multiple variables
+/// let env = EnvBuilder::builder()
+///     .env("A", "1")
+///     .env("GREETING", "hello world")
+///     .build()
+///     .unwrap();
+/// assert_eq!(env.to_string(), r#"ENV A=1 GREETING="hello world""#);
+///
+/// // empty values are quoted unless bare_empty is set
+/// let env = EnvBuilder::builder()
+///     .key("PROXY")
+///     .value("")
+///     .build()
+///     .unwrap();
+/// assert_eq!(env.to_string(), r#"ENV PROXY="""#);
+///
+/// let env = EnvBuilder::builder()
+///     .key("path")
+///     .value("/usr/bin")
+///     .normalize_name(true)
+///     .build()
+///     .unwrap();
+/// assert_eq!(env.to_string(), "ENV PATH=/usr/bin");
+///
+/// let env = EnvBuilder::builder()
+///     .key("GREETING")
+///     .value("hello world")
+///     .legacy_form(true)
+///     .build()
+///     .unwrap();
+/// assert_eq!(env.to_string(), "ENV GREETING hello world");
+/// ```
+///
+/// An `EnvBuilder` can also be created from a map or a vec of key/value pairs. Use a `BTreeMap`
+/// or a `Vec` for a stable ordering, `HashMap` iteration order is arbitrary.
+/// ```
+/// # use dockerfile_builder::instruction_builder::EnvBuilderInner;
+/// # use std::collections::BTreeMap;
+/// let envs = BTreeMap::from([("B", "2"), ("A", "1")]);
+/// let env = EnvBuilderInner::from(envs).build().unwrap();
+/// assert_eq!(env.to_string(), "ENV A=1 B=2");
 /// ```
 ///
 /// [ENV]: dockerfile_builder::instruction::ENV
@@ -174,28 +411,129 @@ impl FromBuilder {
     value_method = value,
 )]
 pub struct EnvBuilder {
-    pub key: String,
-    pub value: String,
+    pub key: Option<String>,
+    pub value: Option<String>,
+    pub envs: Option<Vec<(String, String)>>,
+    pub bare_empty: Option<bool>,
+    pub normalize_name: Option<bool>,
+    pub legacy_form: Option<bool>,
+}
+
+impl EnvBuilder {
+    fn value(&self) -> BuildResult<String> {
+        let envs = key_value_pairs("ENV", &self.key, &self.value, &self.envs)?;
+        if self.legacy_form == Some(true) {
+            return self.legacy_value(envs);
+        }
+        Ok(render_pairs(envs, |key, value| {
+            let value = match value.is_empty() {
+                true => empty_value(self.bare_empty),
+                false => quote_value(value),
+            };
+            format!("{}={}", normalize_name(key, self.normalize_name), value)
+        }))
+    }
+}
+
+impl EnvBuilder {
+    /// Renders the single variable as `<key> <value>`
+    fn legacy_value(&self, envs: Vec<(&str, &str)>) -> BuildResult<String> {
+        let (key, value) = envs[0];
+        if envs.len() > 1 {
+            return Err(invalid!(
+                "ENV legacy form only supports a single key/value pair"
+            ));
+        }
+        if value.trim().is_empty() || value.contains('\n') {
+            return Err(invalid!(
+                "ENV legacy form requires a non-empty, single line value"
+            ));
+        }
+        Ok(format!(
+            "{} {}",
+            normalize_name(key, self.normalize_name),
+            value
+        ))
+    }
+}
+
+impl EnvBuilderInner {
+    /// Adds a variable to the envs
+    pub fn env<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) -> &mut Self {
+        self.envs
+            .get_or_insert_with(Vec::new)
+            .push((key.into(), value.into()));
+        self
+    }
 }
 
 impl EnvBuilder {
-    fn value(&self) -> Result<String> {
-        Ok(format!("{}={}", self.key, self.value))
+    /// Starts building an `ENV` setting the variables of `envs`, in key order
+    ///
+    /// [`ENV`] itself can't implement `From<BTreeMap<_, _>>` since it converts from any
+    /// `Into<String>`, build it from the map with this method instead.
+    ///
+    /// Example:
+    /// ```
+    /// # use dockerfile_builder::instruction_builder::EnvBuilder;
+    /// # use std::collections::BTreeMap;
+    /// let envs = BTreeMap::from([("RUST_LOG".to_string(), "info".to_string())]);
+    /// let env = EnvBuilder::from_map(envs).build().unwrap();
+    /// assert_eq!(env.to_string(), "ENV RUST_LOG=info");
+    /// ```
+    pub fn from_map<K: Into<String>, V: Into<String>>(envs: BTreeMap<K, V>) -> EnvBuilderInner {
+        EnvBuilderInner::from(envs)
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> From<Vec<(K, V)>> for EnvBuilderInner {
+    fn from(envs: Vec<(K, V)>) -> Self {
+        let mut builder = EnvBuilder::builder();
+        for (key, value) in envs {
+            builder.env(key, value);
+        }
+        builder
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> From<BTreeMap<K, V>> for EnvBuilderInner {
+    fn from(envs: BTreeMap<K, V>) -> Self {
+        EnvBuilderInner::from(envs.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> From<HashMap<K, V>> for EnvBuilderInner {
+    fn from(envs: HashMap<K, V>) -> Self {
+        EnvBuilderInner::from(envs.into_iter().collect::<Vec<_>>())
     }
 }
 
 /// Builder struct for [`RUN`] instruction (shell form)
 ///
 /// * `RunBuilder` constructs the shell form for [`RUN`] by default.
-/// To construct the exec form of `RUN`, use [`RunExecBuilder`].
+///   To construct the exec form of `RUN`, use [`RunExecBuilder`].
 ///
 /// Format according to [Dockerfile
 /// reference](https://docs.docker.com/engine/reference/builder/#run):
-/// * `RUN command`
+/// * `RUN [--mount=<mount>] [--network=<network>] [--security=<security>] command`
+///
+/// Set `exec_shell` to render the commands in exec form, run by the given shell, e.g. the
+/// [active shell](crate::Dockerfile::active_shell) of the Dockerfile:
+/// `RUN ["<shell>", "<shell flag>", "command"]`. This keeps the behavior of `RUN` the same
+/// whatever `SHELL` is set before it. Heredocs can't be used in exec form.
+///
+/// Commands are joined with ` && ` on a single line by default, set `separator` to change it,
+/// see [`Separator`].
+///
+/// Set `wrap_at` to wrap lines longer than the given number of columns: `\` continuations are
+/// inserted between words, never inside a quoted string, so a long quoted string or word may
+/// still exceed the width. Lines are not wrapped by default, nor in exec form or with heredocs.
 ///
 /// Example:
 /// ```
-/// # use dockerfile_builder::instruction_builder::RunBuilder;
+/// # use dockerfile_builder::instruction_builder::{
+/// #     Mount, Network, RunBuilder, Security, Separator, TmpfsMount,
+/// # };
 /// // build RUN with a single command
 /// let run = RunBuilder::builder()
 ///     .command("source $HOME/.bashrc")
@@ -220,10 +558,61 @@ impl EnvBuilder {
 ///     run.to_string(),
 ///     "RUN source $HOME/.bashrc && echo $HOME",
 /// );
+///
+/// // build RUN with a mount
+/// let run = RunBuilder::builder()
+///     .mount(Mount::Tmpfs(TmpfsMount {
+///         target: "/tmp".to_string(),
+///         size: Some("100m".to_string()),
+///     }))
+///     .command("make")
+///     .build().unwrap();
+/// assert_eq!(
+///     run.to_string(),
+///     "RUN --mount=type=tmpfs,target=/tmp,size=100m make",
+/// );
+///
+/// // build RUN without network access
+/// let run = RunBuilder::builder()
+///     .network(Network::None)
+///     .command("make test")
+///     .build().unwrap();
+/// assert_eq!(run.to_string(), "RUN --network=none make test");
+///
+/// // build RUN with elevated privileges, requires BuildKit and the `security.insecure`
+/// // entitlement, e.g. `# syntax=docker/dockerfile:1-labs`
+/// let run = RunBuilder::builder()
+///     .security(Security::Insecure)
+///     .command("mount -t tmpfs none /mnt")
+///     .build().unwrap();
+/// assert_eq!(run.to_string(), "RUN --security=insecure mount -t tmpfs none /mnt");
+///
+/// // build RUN with one command per line, run even if the previous one fails
+/// let run = RunBuilder::builder()
+///     .commands(vec!["make test", "make clean"])
+///     .separator(Separator::Semicolon)
+///     .build().unwrap();
+/// assert_eq!(run.to_string(), "RUN make test; \\\n    make clean");
+///
+/// // build RUN in exec form, run by bash
+/// let run = RunBuilder::builder()
+///     .exec_shell(vec!["/bin/bash", "-c"])
+///     .command("echo $HOME")
+///     .build().unwrap();
+/// assert_eq!(run.to_string(), r#"RUN ["/bin/bash", "-c", "echo $HOME"]"#);
+///
+/// // build RUN with lines of at most 40 columns
+/// let run = RunBuilder::builder()
+///     .command("apt-get install -y curl git 'build essential'")
+///     .wrap_at(40)
+///     .build().unwrap();
+/// assert_eq!(
+///     run.to_string(),
+///     "RUN apt-get install -y curl git \\\n    'build essential'",
+/// );
 /// ```
 ///
 /// [RUN]: dockerfile_builder::instruction::RUN
-// TODO: Flag options for RUN
 #[derive(Debug, InstructionBuilder)]
 #[instruction_builder(
     instruction_name = RUN,
@@ -232,22 +621,462 @@ impl EnvBuilder {
 pub struct RunBuilder {
     #[instruction_builder(each = command)]
     pub commands: Vec<String>,
+    #[instruction_builder(each = mount)]
+    pub mounts: Option<Vec<Mount>>,
+    pub network: Option<Network>,
+    pub security: Option<Security>,
+    pub exec_shell: Option<Vec<String>>,
+    pub separator: Option<Separator>,
+    pub wrap_at: Option<usize>,
 }
 
 impl RunBuilder {
-    fn value(&self) -> Result<String> {
-        Ok(self.commands.join(" && "))
+    fn value(&self) -> BuildResult<String> {
+        let mounts = self
+            .mounts
+            .iter()
+            .flatten()
+            .map(|m| format!("--mount={} ", m))
+            .collect::<String>();
+        let network = network_flag(&self.network);
+        let security = match &self.security {
+            Some(security) => format!("--security={} ", security),
+            None => String::new(),
+        };
+        // Heredoc bodies can't be chained inline, they follow the line holding all commands
+        let mut heads = vec![];
+        let mut bodies = vec![];
+        for command in &self.commands {
+            match command.split_once('\n') {
+                Some((head, body)) if head.contains("<<") => {
+                    heads.push(head);
+                    bodies.push(body);
+                }
+                _ => heads.push(command),
+            }
+        }
+        if let Some(shell) = &self.exec_shell {
+            if shell.is_empty() {
+                return Err(invalid!("exec_shell cannot be empty"));
+            }
+            if !bodies.is_empty() {
+                return Err(invalid!("heredocs cannot be used with exec_shell"));
+            }
+            let mut args = shell[1..].to_vec();
+            args.push(heads.join(separator(self.separator, true)));
+            let exec = exec_array(Some(&shell[0]), &Some(args));
+            return Ok(format!("{}{}{}{}", mounts, network, security, exec));
+        }
+        // The heredoc bodies must follow the line holding the heredoc
+        let single_line = !bodies.is_empty();
+        let commands = heads.join(separator(self.separator, single_line));
+        let mut value = format!("{}{}{}{}", mounts, network, security, commands);
+        if let (Some(columns), false) = (self.wrap_at, single_line) {
+            value = value
+                .lines()
+                .enumerate()
+                // The first line follows `RUN `
+                .map(|(i, line)| wrap_line(line, if i == 0 { 4 } else { 0 }, columns))
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+        for body in bodies {
+            value.push('\n');
+            value.push_str(body);
+        }
+        Ok(value)
+    }
+}
+
+impl RunBuilderInner {
+    /// Adds the commands of a [`Script`] built by the given closure
+    ///
+    /// Example:
+    /// ```
+    /// # use dockerfile_builder::instruction_builder::RunBuilder;
+    /// let dev = true;
+    /// let run = RunBuilder::builder()
+    ///     .script(|s| {
+    ///         s.cmd("apt-get update");
+    ///         if dev {
+    ///             s.cmd("apt-get install -y gdb");
+    ///         }
+    ///     })
+    ///     .build().unwrap();
+    /// assert_eq!(run.to_string(), "RUN apt-get update && apt-get install -y gdb");
+    /// ```
+    pub fn script<F: FnOnce(&mut Script)>(&mut self, f: F) -> &mut Self {
+        let mut script = Script::default();
+        f(&mut script);
+        for command in script.commands {
+            self.command(command);
+        }
+        self
+    }
+
+    /// Adds a command writing `content` to the file at `path`, using a `cat` heredoc
+    ///
+    /// The heredoc delimiter is quoted so the content is written as is, without variable
+    /// expansion. If a line of the content is `EOF`, a numbered delimiter is used instead.
+    /// `path` is quoted with [`shell_quote`] if needed.
+    ///
+    /// Heredocs in `RUN` require BuildKit with `# syntax=docker/dockerfile:1.4` or later, see
+    /// [`Dockerfile::syntax`](crate::Dockerfile::syntax).
+    ///
+    /// Example:
+    /// ```
+    /// # use dockerfile_builder::instruction_builder::RunBuilder;
+    /// let run = RunBuilder::builder()
+    ///     .write_file("/etc/app.conf", "port = 80\nhost = $HOST")
+    ///     .command("app --check")
+    ///     .build().unwrap();
+    /// assert_eq!(
+    ///     run.to_string(),
+    ///     "RUN cat <<'EOF' > /etc/app.conf && app --check\nport = 80\nhost = $HOST\nEOF",
+    /// );
+    /// ```
+    pub fn write_file<P: Into<String>, C: Into<String>>(
+        &mut self,
+        path: P,
+        content: C,
+    ) -> &mut Self {
+        let content = content.into();
+        let content = content.strip_suffix('\n').unwrap_or(&content);
+        let delimiter = heredoc_delimiter(content);
+        self.command(format!(
+            "cat <<'{delimiter}' > {}\n{content}\n{delimiter}",
+            shell_quote(&path.into())
+        ))
+    }
+}
+
+/// Mount for the `RUN --mount` flag of [`RunBuilder`]
+///
+/// Format according to [Dockerfile
+/// reference](https://docs.docker.com/engine/reference/builder/#run---mount):
+/// * `--mount=[type=<TYPE>][,option=<value>[,option=<value>]...]`
+///
+/// Example:
+/// ```
+/// # use dockerfile_builder::instruction_builder::{CacheMount, Mount, RunBuilder, Sharing};
+/// let run = RunBuilder::builder()
+///     .mount(Mount::Cache(CacheMount {
+///         target: "/root/.cargo".to_string(),
+///         sharing: Some(Sharing::Locked),
+///         ..Default::default()
+///     }))
+///     .command("cargo build")
+///     .build().unwrap();
+/// assert_eq!(
+///     run.to_string(),
+///     "RUN --mount=type=cache,target=/root/.cargo,sharing=locked cargo build",
+/// );
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Mount {
+    Bind(BindMount),
+    Cache(CacheMount),
+    Secret(SecretMount),
+    Tmpfs(TmpfsMount),
+}
+
+impl Display for Mount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let options = match self {
+            Mount::Bind(m) => vec![
+                Some("type=bind".to_string()),
+                Some(format!("target={}", m.target)),
+                m.source.as_ref().map(|s| format!("source={}", s)),
+                m.from.as_ref().map(|s| format!("from={}", s)),
+                m.read_write.map(|rw| format!("rw={}", rw)),
+            ],
+            Mount::Cache(m) => vec![
+                Some("type=cache".to_string()),
+                Some(format!("target={}", m.target)),
+                m.id.as_ref().map(|s| format!("id={}", s)),
+                m.sharing.as_ref().map(|s| format!("sharing={}", s)),
+                m.source.as_ref().map(|s| format!("source={}", s)),
+                m.from.as_ref().map(|s| format!("from={}", s)),
+            ],
+            Mount::Secret(m) => vec![
+                Some("type=secret".to_string()),
+                m.id.as_ref().map(|s| format!("id={}", s)),
+                m.target.as_ref().map(|s| format!("target={}", s)),
+                m.required.map(|r| format!("required={}", r)),
+            ],
+            Mount::Tmpfs(m) => vec![
+                Some("type=tmpfs".to_string()),
+                Some(format!("target={}", m.target)),
+                m.size.as_ref().map(|s| format!("size={}", s)),
+            ],
+        };
+        write!(
+            f,
+            "{}",
+            options.into_iter().flatten().collect::<Vec<_>>().join(",")
+        )
+    }
+}
+
+/// `RUN --mount=type=bind` mount, binds files or directories from the context or another stage
+///
+/// * `target`: Mount path.
+/// * `source`: Source path in `from`. Defaults to the root of `from`.
+/// * `from`: Build stage or image name for the root of the source. Defaults to the build context.
+/// * `read_write`: Allow writes on the mount. Written data is discarded.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct BindMount {
+    pub target: String,
+    pub source: Option<String>,
+    pub from: Option<String>,
+    pub read_write: Option<bool>,
+}
+
+/// `RUN --mount=type=cache` mount, caches directories for compilers and package managers
+///
+/// * `target`: Mount path.
+/// * `id`: Id to identify separate caches. Defaults to `target`.
+/// * `sharing`: How concurrent builds use the cache, see [`Sharing`].
+/// * `source`: Subpath in `from` to mount.
+/// * `from`: Build stage to use as the base of the cache mount.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct CacheMount {
+    pub target: String,
+    pub id: Option<String>,
+    pub sharing: Option<Sharing>,
+    pub source: Option<String>,
+    pub from: Option<String>,
+}
+
+/// Sharing mode of a [`CacheMount`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Sharing {
+    /// Multiple writers use the cache at the same time
+    Shared,
+    /// A new mount is created if there are multiple writers
+    Private,
+    /// The second writer waits for the first one to release the mount
+    Locked,
+}
+
+impl Display for Sharing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sharing::Shared => write!(f, "shared"),
+            Sharing::Private => write!(f, "private"),
+            Sharing::Locked => write!(f, "locked"),
+        }
+    }
+}
+
+/// `RUN --mount=type=secret` mount, exposes a secret without baking it into the image
+///
+/// * `id`: Id of the secret. Defaults to the basename of `target`.
+/// * `target`: Mount path. Defaults to `/run/secrets/` + `id`.
+/// * `required`: Fail the build if the secret is unavailable.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SecretMount {
+    pub id: Option<String>,
+    pub target: Option<String>,
+    pub required: Option<bool>,
+}
+
+/// `RUN --mount=type=tmpfs` mount, used as scratch space during the build
+///
+/// * `target`: Mount path.
+/// * `size`: Upper limit on the size of the filesystem, e.g. `100m`.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct TmpfsMount {
+    pub target: String,
+    pub size: Option<String>,
+}
+
+/// Network mode for the `RUN --network` flag of [`RunBuilder`] and [`RunExecBuilder`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Network {
+    /// Run in the default network
+    Default,
+    /// Run with no network access
+    None,
+    /// Run in the host's network environment
+    Host,
+}
+
+impl Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Network::Default => write!(f, "default"),
+            Network::None => write!(f, "none"),
+            Network::Host => write!(f, "host"),
+        }
+    }
+}
+
+/// Security mode for the `RUN --security` flag of [`RunBuilder`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Security {
+    /// Run in the default sandbox
+    Sandbox,
+    /// Run without sandbox, similar to `docker run --privileged`
+    Insecure,
+}
+
+impl Display for Security {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Security::Sandbox => write!(f, "sandbox"),
+            Security::Insecure => write!(f, "insecure"),
+        }
+    }
+}
+
+/// Separator between the commands of [`RunBuilder`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Separator {
+    /// ` && \` and a new line: stops at the first failing command
+    AndAnd,
+    /// `; \` and a new line: runs every command, even after a failure
+    Semicolon,
+    /// ` && ` on a single line, the default
+    SingleLine,
+}
+
+/// Returns the separator between commands, forced on a single line if `single_line` is set
+fn separator(separator: Option<Separator>, single_line: bool) -> &'static str {
+    match (separator, single_line) {
+        (Some(Separator::AndAnd), false) => " && \\\n    ",
+        (Some(Separator::Semicolon), false) => "; \\\n    ",
+        (Some(Separator::Semicolon), true) => "; ",
+        _ => " && ",
+    }
+}
+
+/// Wraps `line`, starting at column `offset`, with `\` continuations between unquoted words so
+/// that it fits in `columns` where possible
+fn wrap_line(line: &str, offset: usize, columns: usize) -> String {
+    let (line, continued) = match line.strip_suffix(" \\") {
+        Some(line) => (line, true),
+        None => (line, false),
+    };
+    let content = line.trim_start();
+    let indent = &line[..line.len() - content.len()];
+
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut quote = None;
+    let mut escaped = false;
+    for c in content.chars() {
+        match (c, quote) {
+            _ if escaped => escaped = false,
+            ('\\', q) if q != Some('\'') => escaped = true,
+            ('\'' | '"', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (' ', None) => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    let mut wrapped = vec![];
+    let mut current = indent.to_string();
+    let mut width = offset + indent.chars().count();
+    for (i, word) in words.iter().enumerate() {
+        let is_last = i == words.len() - 1;
+        let len = word.chars().count();
+        if !current.trim_start().is_empty() {
+            // Leave room for ` \` unless this is the end of the line
+            let reserved = if is_last && !continued { 0 } else { 2 };
+            if width + 1 + len + reserved > columns {
+                wrapped.push(std::mem::take(&mut current));
+                current.push_str("    ");
+                width = 4;
+            } else {
+                current.push(' ');
+                width += 1;
+            }
+        }
+        current.push_str(word);
+        width += len;
+    }
+    if continued {
+        current.push_str(" \\");
+    }
+    wrapped.push(current);
+    wrapped.join(" \\\n")
+}
+
+fn network_flag(network: &Option<Network>) -> String {
+    match network {
+        Some(network) => format!("--network={} ", network),
+        None => String::new(),
+    }
+}
+
+/// Quotes a string for safe use as a single word in a shell command, e.g. in [`RunBuilder`]
+///
+/// Strings made only of alphanumerics and `@%+=:,./-_` are returned unchanged. Any other string
+/// is wrapped in single quotes, and its single quotes are escaped as `'"'"'`.
+///
+/// Example:
+/// ```
+/// # use dockerfile_builder::instruction_builder::{shell_quote, RunBuilder};
+/// let name = "it's; rm -rf /";
+/// let run = RunBuilder::builder()
+///     .command(format!("echo {}", shell_quote(name)))
+///     .build().unwrap();
+/// assert_eq!(run.to_string(), r#"RUN echo 'it'"'"'s; rm -rf /'"#);
+/// ```
+pub fn shell_quote(s: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "@%+=:,./-_".contains(c);
+    if !s.is_empty() && s.chars().all(is_safe) {
+        return s.to_string();
+    }
+    format!("'{}'", s.replace('\'', r#"'"'"'"#))
+}
+
+/// Returns a heredoc delimiter that doesn't collide with a line of `content`
+fn heredoc_delimiter(content: &str) -> String {
+    let mut delimiter = "EOF".to_string();
+    let mut n = 0;
+    while content.lines().any(|line| line.trim() == delimiter) {
+        n += 1;
+        delimiter = format!("EOF{}", n);
+    }
+    delimiter
+}
+
+/// Script of shell commands for [`RunBuilder`]
+///
+/// See [`RunBuilderInner::script`]
+#[derive(Debug, Default)]
+pub struct Script {
+    commands: Vec<String>,
+}
+
+impl Script {
+    /// Adds a command to the end of the script
+    pub fn cmd<T: Into<String>>(&mut self, command: T) -> &mut Self {
+        self.commands.push(command.into());
+        self
     }
 }
 
 /// Builder struct for [`RUN`] instruction (exec form)
 ///
 /// * RunBuilder constructs the exec form for [`RUN`].
-/// To construct the shell form, use [`RunBuilder`].
+///   To construct the shell form, use [`RunBuilder`].
 ///
 /// Format according to [Dockerfile
 /// reference](https://docs.docker.com/engine/reference/builder/#run):
-/// * `RUN ["executable", "param1", "param2"]`
+/// * `RUN [--network=<network>] ["executable", "param1", "param2"]`
 ///
 /// Example:
 /// ```
@@ -266,6 +1095,15 @@ impl RunBuilder {
 ///     .params(vec!["-f", "-c"])
 ///     .build().unwrap();
 /// assert_eq!(run.to_string(), r#"RUN ["mybin.exe", "-f", "-c"]"#);
+///
+/// // build RUN running a command with `/bin/sh`, stopping at the first error
+/// let run = RunExecBuilder::builder()
+///     .sh_wrapped("apt-get update && apt-get install -y curl", true)
+///     .build().unwrap();
+/// assert_eq!(
+///     run.to_string(),
+///     r#"RUN ["/bin/sh", "-euc", "apt-get update && apt-get install -y curl"]"#,
+/// );
 /// ```
 ///
 /// [RUN]: dockerfile_builder::instruction::RUN
@@ -278,28 +1116,100 @@ pub struct RunExecBuilder {
     pub executable: String,
     #[instruction_builder(each = param)]
     pub params: Option<Vec<String>>,
+    pub network: Option<Network>,
 }
 
 impl RunExecBuilder {
-    fn value(&self) -> Result<String> {
-        let params = match self.params.clone() {
-            Some(param_vec) => {
-                if param_vec.is_empty() {
-                    String::new()
-                } else {
-                    format!(r#", "{}""#, param_vec.join(r#"", ""#))
-                }
-            }
-            None => String::new(),
-        };
-        Ok(format!(r#"["{}"{}]"#, self.executable, params))
+    fn value(&self) -> BuildResult<String> {
+        Ok(format!(
+            "{}{}",
+            network_flag(&self.network),
+            exec_array(Some(&self.executable), &self.params)
+        ))
     }
 }
 
-/// Builder struct for [`CMD`] instruction (shell form)
-///
+impl RunExecBuilderInner {
+    /// Runs `command` with `/bin/sh -c`, making the use of shell features explicit in exec form
+    ///
+    /// If `strict` is set, the shell is run with `-eu`: it exits at the first failing command or
+    /// unset variable. Replaces the executable and params set before.
+    pub fn sh_wrapped<T: Into<String>>(&mut self, command: T, strict: bool) -> &mut Self {
+        let flags = if strict { "-euc" } else { "-c" };
+        self.executable("/bin/sh");
+        self.params(vec![flags.to_string(), command.into()]);
+        self
+    }
+}
+
+/// Builder struct for [`RUN`] instruction (heredoc form)
+///
+/// * `RunHeredocBuilder` constructs a `RUN` running a multi-line script, which requires
+///   BuildKit with `# syntax=docker/dockerfile:1.4` or later.
+///   To chain commands on a single line, use [`RunBuilder`].
+///
+/// Format according to [Dockerfile
+/// reference](https://docs.docker.com/engine/reference/builder/#here-documents):
+/// * `RUN <<<delimiter>`, followed by the script lines and the closing `<delimiter>`
+///
+/// `delimiter` defaults to `EOF`. `interpreter` is rendered as the first script line, e.g.
+/// `#!/bin/bash`.
+///
+/// Example:
+/// ```
+/// # use dockerfile_builder::instruction_builder::RunHeredocBuilder;
+/// let run = RunHeredocBuilder::builder()
+///     .interpreter("#!/bin/bash")
+///     .line("set -e")
+///     .line("apt-get update")
+///     .line("apt-get install -y curl")
+///     .build().unwrap();
+/// assert_eq!(
+///     run.to_string(),
+///     "RUN <<EOF\n#!/bin/bash\nset -e\napt-get update\napt-get install -y curl\nEOF",
+/// );
+/// ```
+///
+/// [RUN]: dockerfile_builder::instruction::RUN
+#[derive(Debug, InstructionBuilder)]
+#[instruction_builder(
+    instruction_name = RUN,
+    value_method = value,
+)]
+pub struct RunHeredocBuilder {
+    #[instruction_builder(each = line)]
+    pub lines: Vec<String>,
+    #[instruction_builder(default = "EOF")]
+    pub delimiter: String,
+    pub interpreter: Option<String>,
+}
+
+impl RunHeredocBuilder {
+    fn value(&self) -> BuildResult<String> {
+        if self.lines.is_empty() {
+            return Err(invalid!("RUN heredoc requires at least one line"));
+        }
+        let delimiter = &self.delimiter;
+        if self.lines.iter().any(|line| line.trim() == delimiter) {
+            return Err(invalid!(
+                "RUN heredoc line must not be the delimiter `{}`",
+                delimiter
+            ));
+        }
+        let body = self
+            .interpreter
+            .iter()
+            .chain(&self.lines)
+            .map(|line| format!("{}\n", line))
+            .collect::<String>();
+        Ok(format!("<<{}\n{}{}", delimiter, body, delimiter))
+    }
+}
+
+/// Builder struct for [`CMD`] instruction (shell form)
+///
 /// * CmdBuilder constructs the shell form for [`CMD`] by default.
-/// To construct the exec form or CMD in combination with ENTRYPOINT, use [`CmdExecBuilder`].
+///   To construct the exec form or CMD in combination with ENTRYPOINT, use [`CmdExecBuilder`].
 ///
 /// Format according to [Dockerfile
 /// reference](https://docs.docker.com/engine/reference/builder/#cmd):
@@ -337,7 +1247,7 @@ pub struct CmdBuilder {
 }
 
 impl CmdBuilder {
-    fn value(&self) -> Result<String> {
+    fn value(&self) -> BuildResult<String> {
         let params = match self.params.clone() {
             Some(param_vec) => {
                 if param_vec.is_empty() {
@@ -355,12 +1265,12 @@ impl CmdBuilder {
 /// Builder struct for [`CMD`] instruction (exec form)
 ///
 /// * CmdBuilder constructs the exec form for [`CMD`].
-/// To construct the shell form, use [`CmdBuilder`].
+///   To construct the shell form, use [`CmdBuilder`].
 ///
 /// Format according to [Dockerfile
 /// reference](https://docs.docker.com/engine/reference/builder/#cmd):
 /// * `CMD ["executable", "param1", "param2"]`
-/// OR
+///   OR
 /// * `CMD ["param1","param2"]` (as default parameters to ENTRYPOINT)
 ///
 /// Example:
@@ -394,32 +1304,12 @@ pub struct CmdExecBuilder {
 }
 
 impl CmdExecBuilder {
-    fn value(&self) -> Result<String> {
-        if self.executable.is_none() && self.params.is_none() {
-            return Err(eyre!("CMD cannot be empty"));
+    fn value(&self) -> BuildResult<String> {
+        let no_params = self.params.as_ref().is_none_or(Vec::is_empty);
+        if self.executable.is_none() && no_params {
+            return Err(invalid!("CMD cannot be empty"));
         }
-        let params = match self.params.clone() {
-            Some(param_vec) => {
-                if self.executable.is_none() && param_vec.is_empty() {
-                    return Err(eyre!("CMD cannot be empty"));
-                } else if param_vec.is_empty() {
-                    String::new()
-                } else if self.executable.is_none() {
-                    format!(r#""{}""#, param_vec.join(r#"", ""#))
-                } else {
-                    format!(r#", "{}""#, param_vec.join(r#"", ""#))
-                }
-            }
-            None => String::new(),
-        };
-        Ok(format!(
-            r#"[{}{}]"#,
-            self.executable
-                .as_ref()
-                .map(|e| format!(r#""{}""#, e))
-                .unwrap_or_default(),
-            params,
-        ))
+        Ok(exec_array(self.executable.as_deref(), &self.params))
     }
 }
 
@@ -427,7 +1317,10 @@ impl CmdExecBuilder {
 ///
 /// Format according to [Dockerfile
 /// reference](https://docs.docker.com/engine/reference/builder/#label):
-/// * `LABEL <key>=<value>`
+/// * `LABEL <key>=<value> <key>=<value> <key>=<value> ...`
+///
+/// Values containing whitespace, `=`, quotes or backslashes are wrapped in double quotes, simple
+/// values are rendered as is.
 ///
 /// Example:
 /// ```
@@ -438,26 +1331,44 @@ impl CmdExecBuilder {
 ///     .build()
 ///     .unwrap();
 /// assert_eq!(label.to_string(), "LABEL foo=bar");
+///
+/// // build LABEL with multiple key/value pairs
+/// let label = LabelBuilder::builder()
+///     .label("version", "1.0")
+///     .label("description", "My App")
+///     .build()
+///     .unwrap();
+/// assert_eq!(label.to_string(), r#"LABEL version=1.0 description="My App""#);
 /// ```
 ///
 /// [LABEL]: dockerfile_builder::instruction::LABEL
-///
-// TODO: The official format is
-// * `LABEL <key>=<value> <key>=<value> <key>=<value> ...`
-// Use `each` to support the multiple format.
 #[derive(Debug, InstructionBuilder)]
 #[instruction_builder(
     instruction_name = LABEL,
     value_method = value,
 )]
 pub struct LabelBuilder {
-    pub key: String,
-    pub value: String,
+    pub key: Option<String>,
+    pub value: Option<String>,
+    pub labels: Option<Vec<(String, String)>>,
 }
 
 impl LabelBuilder {
-    fn value(&self) -> Result<String> {
-        Ok(format!("{}={}", self.key, self.value))
+    fn value(&self) -> BuildResult<String> {
+        let labels = key_value_pairs("LABEL", &self.key, &self.value, &self.labels)?;
+        Ok(render_pairs(labels, |key, value| {
+            format!("{}={}", key, quote_value(value))
+        }))
+    }
+}
+
+impl LabelBuilderInner {
+    /// Adds a key/value pair to the labels
+    pub fn label<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) -> &mut Self {
+        self.labels
+            .get_or_insert_with(Vec::new)
+            .push((key.into(), value.into()));
+        self
     }
 }
 
@@ -466,18 +1377,44 @@ impl LabelBuilder {
 /// Format according to [Dockerfile
 /// reference](https://docs.docker.com/engine/reference/builder/#expose):
 /// * `EXPOSE <port>`
-/// or
+///   or
 /// * `EXPOSE <port>/<protocol>`
 ///
+/// A range of ports is rendered as `<port>-<end_port>`, `end_port` must not be less than `port`.
+/// More ports can be added with `port_proto()`, they are rendered after `port`, separated by
+/// spaces. At least one of `port` or `port_proto()` must be set.
+///
 /// Example:
 /// ```
-/// # use dockerfile_builder::instruction_builder::ExposeBuilder;
+/// # use dockerfile_builder::instruction_builder::{ExposeBuilder, Protocol};
 /// let expose = ExposeBuilder::builder()
 ///     .port(80)
-///     .protocol("udp")
+///     .protocol(Protocol::Udp)
 ///     .build()
 ///     .unwrap();
 /// assert_eq!(expose.to_string(), "EXPOSE 80/udp");
+///
+/// let expose = ExposeBuilder::builder()
+///     .port_range(8000, 8010)
+///     .protocol_name("tcp")
+///     .build()
+///     .unwrap();
+/// assert_eq!(expose.to_string(), "EXPOSE 8000-8010/tcp");
+///
+/// let expose = ExposeBuilder::builder()
+///     .port(80)
+///     .port_proto(443, Some("tcp"))
+///     .port_proto(8080, None)
+///     .build()
+///     .unwrap();
+/// assert_eq!(expose.to_string(), "EXPOSE 80 443/tcp 8080");
+///
+/// let expose = ExposeBuilder::builder()
+///     .port_proto(80, None)
+///     .port_proto(443, Some("tcp"))
+///     .build()
+///     .unwrap();
+/// assert_eq!(expose.to_string(), "EXPOSE 80 443/tcp");
 /// ```
 ///
 /// [EXPOSE]: dockerfile_builder::instruction::EXPOSE
@@ -487,20 +1424,111 @@ impl LabelBuilder {
     value_method = value,
 )]
 pub struct ExposeBuilder {
-    pub port: u16,
-    pub protocol: Option<String>,
+    pub port: Option<u16>,
+    pub end_port: Option<u16>,
+    pub protocol: Option<Protocol>,
+    pub ports: Option<Vec<(u16, Option<Protocol>)>>,
 }
 
 impl ExposeBuilder {
-    fn value(&self) -> Result<String> {
-        Ok(format!(
-            "{}{}",
-            self.port,
-            self.protocol
-                .as_ref()
-                .map(|p| format!("/{}", p))
-                .unwrap_or_default()
-        ))
+    fn value(&self) -> BuildResult<String> {
+        let mut ports = Vec::new();
+        match self.port {
+            Some(port) => {
+                let range = match self.end_port {
+                    Some(end) if end < port => {
+                        return Err(invalid!(
+                            "port range start {} is greater than its end {}",
+                            port,
+                            end
+                        ))
+                    }
+                    Some(end) => format!("-{}", end),
+                    None => String::new(),
+                };
+                ports.push(format!(
+                    "{}{}{}",
+                    port,
+                    range,
+                    protocol_suffix(self.protocol.as_ref())
+                ));
+            }
+            None if self.end_port.is_some() || self.protocol.is_some() => {
+                return Err(invalid!("EXPOSE end_port and protocol require port"));
+            }
+            None => {}
+        }
+        for (port, protocol) in self.ports.iter().flatten() {
+            ports.push(format!("{}{}", port, protocol_suffix(protocol.as_ref())));
+        }
+        if ports.is_empty() {
+            return Err(invalid!("EXPOSE requires at least one port"));
+        }
+        Ok(ports.join(" "))
+    }
+}
+
+fn protocol_suffix(protocol: Option<&Protocol>) -> String {
+    protocol.map(|p| format!("/{}", p)).unwrap_or_default()
+}
+
+impl ExposeBuilderInner {
+    /// Sets the range of ports from `start` to `end`, inclusive
+    pub fn port_range(&mut self, start: u16, end: u16) -> &mut Self {
+        self.port(start).end_port(end)
+    }
+
+    /// Sets the protocol by name, see [`Protocol::from_name`]
+    pub fn protocol_name<T: Into<String>>(&mut self, name: T) -> &mut Self {
+        self.protocol(Protocol::from_name(name))
+    }
+
+    /// Adds a port with an optional protocol name, see [`Protocol::from_name`]
+    pub fn port_proto(&mut self, port: u16, protocol: Option<&str>) -> &mut Self {
+        self.ports
+            .get_or_insert_with(Vec::new)
+            .push((port, protocol.map(Protocol::from_name)));
+        self
+    }
+}
+
+/// Transport protocol of an [`ExposeBuilder`] port
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Protocol {
+    /// `tcp`
+    Tcp,
+    /// `udp`
+    Udp,
+    /// Any other protocol, rendered as is
+    Custom(String),
+}
+
+impl Protocol {
+    /// Returns the protocol named `name`, ignoring case, or [`Protocol::Custom`]
+    ///
+    /// ```
+    /// # use dockerfile_builder::instruction_builder::Protocol;
+    /// assert_eq!(Protocol::from_name("UDP"), Protocol::Udp);
+    /// assert_eq!(Protocol::from_name("sctp"), Protocol::Custom("sctp".to_string()));
+    /// ```
+    pub fn from_name<T: Into<String>>(name: T) -> Protocol {
+        let name = name.into();
+        match name.to_lowercase().as_str() {
+            "tcp" => Protocol::Tcp,
+            "udp" => Protocol::Udp,
+            _ => Protocol::Custom(name),
+        }
+    }
+}
+
+impl Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let protocol = match self {
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+            Protocol::Custom(protocol) => protocol,
+        };
+        write!(f, "{}", protocol)
     }
 }
 
@@ -520,6 +1548,22 @@ impl ExposeBuilder {
 ///     .dest("/mydir/")
 ///     .build().unwrap();
 /// assert_eq!(add.to_string(), "ADD --chown=myuser:mygroup --chmod=655 hom* /mydir/");
+///
+/// // chmod_str keeps the mode as written, e.g. with a leading zero
+/// let add = AddBuilder::builder()
+///     .chmod_str("0755")
+///     .src("run.sh")
+///     .dest("/bin/")
+///     .build().unwrap();
+/// assert_eq!(add.to_string(), "ADD --chmod=0755 run.sh /bin/");
+///
+/// // dest must end with `/` when adding multiple sources
+/// let add = AddBuilder::builder()
+///     .src("foo")
+///     .src("bar")
+///     .dest("/dir/")
+///     .build().unwrap();
+/// assert_eq!(add.to_string(), "ADD foo bar /dir/");
 /// ```
 ///
 /// [ADD]: dockerfile_builder::instruction::ADD
@@ -529,25 +1573,32 @@ impl ExposeBuilder {
     value_method = value,
 )]
 pub struct AddBuilder {
-    pub src: String,
+    #[instruction_builder(each = src)]
+    pub srcs: Vec<String>,
     pub dest: String,
     pub chown: Option<String>,
     pub chmod: Option<u16>,
+    pub chmod_str: Option<String>,
 }
 
 impl AddBuilder {
-    fn value(&self) -> Result<String> {
+    fn value(&self) -> BuildResult<String> {
+        if self.srcs.is_empty() {
+            return Err(invalid!("ADD requires at least one source"));
+        }
+        if self.srcs.len() > 1 && !self.dest.ends_with('/') {
+            return Err(invalid!(
+                "ADD dest must end with / when there are multiple sources"
+            ));
+        }
         Ok(format!(
             "{}{}{} {}",
             self.chown
                 .as_ref()
                 .map(|c| format!("--chown={} ", c))
                 .unwrap_or_default(),
-            self.chmod
-                .as_ref()
-                .map(|c| format!("--chmod={} ", c))
-                .unwrap_or_default(),
-            self.src,
+            chmod_flag(self.chmod, &self.chmod_str)?,
+            self.srcs.join(" "),
             self.dest,
         ))
     }
@@ -583,7 +1634,7 @@ pub struct AddHttpBuilder {
 }
 
 impl AddHttpBuilder {
-    fn value(&self) -> Result<String> {
+    fn value(&self) -> BuildResult<String> {
         Ok(format!(
             "{}{} {}",
             self.checksum
@@ -615,7 +1666,7 @@ pub struct AddGitBuilder {
 }
 
 impl AddGitBuilder {
-    fn value(&self) -> Result<String> {
+    fn value(&self) -> BuildResult<String> {
         Ok(format!(
             "{}{} {}",
             self.keep_git_dir
@@ -644,36 +1695,60 @@ impl AddGitBuilder {
 ///     .dest("/somedir/")
 ///     .build().unwrap();
 /// assert_eq!(copy.to_string(), "COPY --chown=55:mygroup --chmod=644 files* /somedir/");
+///
+/// let copy = CopyBuilder::builder()
+///     .chmod_str("0644")
+///     .src("app.conf")
+///     .dest("/etc/")
+///     .build().unwrap();
+/// assert_eq!(copy.to_string(), "COPY --chmod=0644 app.conf /etc/");
+///
+/// // dest must end with `/` when copying multiple sources
+/// let copy = CopyBuilder::builder()
+///     .src("a.txt")
+///     .src("b.txt")
+///     .dest("/app/")
+///     .build().unwrap();
+/// assert_eq!(copy.to_string(), "COPY a.txt b.txt /app/");
 /// ```
 ///
+/// `from` takes the name of a stage or an image, use `from_index` to reference a previous
+/// stage by its index.
+///
 /// [COPY]: dockerfile_builder::instruction::COPY
-// TODO: Add flag [--from=]
 #[derive(Debug, InstructionBuilder)]
 #[instruction_builder(
     instruction_name = COPY,
     value_method = value,
 )]
 pub struct CopyBuilder {
-    pub src: String,
+    #[instruction_builder(each = src)]
+    pub srcs: Vec<String>,
     pub dest: String,
     pub chown: Option<String>,
     pub chmod: Option<u16>,
+    pub chmod_str: Option<String>,
     pub from: Option<String>,
     pub link: Option<bool>,
 }
 
 impl CopyBuilder {
-    fn value(&self) -> Result<String> {
+    fn value(&self) -> BuildResult<String> {
+        if self.srcs.is_empty() {
+            return Err(invalid!("COPY requires at least one source"));
+        }
+        if self.srcs.len() > 1 && !self.dest.ends_with('/') {
+            return Err(invalid!(
+                "COPY dest must end with / when there are multiple sources"
+            ));
+        }
         Ok(format!(
             "{}{}{}{}{} {}",
             self.chown
                 .as_ref()
                 .map(|c| format!("--chown={} ", c))
                 .unwrap_or_default(),
-            self.chmod
-                .as_ref()
-                .map(|c| format!("--chmod={} ", c))
-                .unwrap_or_default(),
+            chmod_flag(self.chmod, &self.chmod_str)?,
             self.link
                 .as_ref()
                 .map(|c| match c {
@@ -685,16 +1760,34 @@ impl CopyBuilder {
                 .as_ref()
                 .map(|c| format!("--from={} ", c))
                 .unwrap_or_default(),
-            self.src,
+            self.srcs.join(" "),
             self.dest,
         ))
     }
 }
 
+impl CopyBuilderInner {
+    /// Sets `--from` to the index of a previous build stage, starting at 0
+    ///
+    /// Example:
+    /// ```
+    /// # use dockerfile_builder::instruction_builder::CopyBuilder;
+    /// let copy = CopyBuilder::builder()
+    ///     .from_index(0)
+    ///     .src("/app")
+    ///     .dest("/app")
+    ///     .build().unwrap();
+    /// assert_eq!(copy.to_string(), "COPY --from=0 /app /app");
+    /// ```
+    pub fn from_index(&mut self, index: u32) -> &mut Self {
+        self.from(index.to_string())
+    }
+}
+
 /// Builder struct for [`ENTRYPOINT`] instruction (shell form)
 ///
 /// * EntrypointBuilder constructs the shell form for [`ENTRYPOINT`] by default.
-/// To construct the exec form, use [`EntrypointExecBuilder`].
+///   To construct the exec form, use [`EntrypointExecBuilder`].
 ///
 /// Format according to [Dockerfile
 /// reference](https://docs.docker.com/engine/reference/builder/#entrypoint):
@@ -732,7 +1825,7 @@ pub struct EntrypointBuilder {
 }
 
 impl EntrypointBuilder {
-    fn value(&self) -> Result<String> {
+    fn value(&self) -> BuildResult<String> {
         let params = match self.params.clone() {
             Some(param_vec) => {
                 if param_vec.is_empty() {
@@ -751,7 +1844,7 @@ impl EntrypointBuilder {
 /// Builder struct for [`ENTRYPOINT`] instruction (exec form)
 ///
 /// * EntrypointExecBuilder constructs the exec form for [`ENTRYPOINT`].
-/// To construct the shell form, use [`EntrypointBuilder`].
+///   To construct the shell form, use [`EntrypointBuilder`].
 ///
 /// Format according to [Dockerfile
 /// reference](https://docs.docker.com/engine/reference/builder/#entrypoint):
@@ -789,18 +1882,8 @@ pub struct EntrypointExecBuilder {
 }
 
 impl EntrypointExecBuilder {
-    fn value(&self) -> Result<String> {
-        let params = match self.params.clone() {
-            Some(param_vec) => {
-                if param_vec.is_empty() {
-                    String::new()
-                } else {
-                    format!(r#", "{}""#, param_vec.join(r#"", ""#))
-                }
-            }
-            None => String::new(),
-        };
-        Ok(format!(r#"["{}"{}]"#, self.executable, params))
+    fn value(&self) -> BuildResult<String> {
+        Ok(exec_array(Some(&self.executable), &self.params))
     }
 }
 
@@ -810,6 +1893,9 @@ impl EntrypointExecBuilder {
 /// reference](https://docs.docker.com/engine/reference/builder/#volume):
 /// * `VOLUME <path>...`
 ///
+/// To construct the JSON form of `VOLUME`, use [`VolumeExecBuilder`]. Paths containing
+/// whitespace are only unambiguous in the JSON form.
+///
 /// [VOLUME]: dockerfile_builder::instruction::VOLUME
 #[derive(Debug, InstructionBuilder)]
 #[instruction_builder(
@@ -822,18 +1908,61 @@ pub struct VolumeBuilder {
 }
 
 impl VolumeBuilder {
-    fn value(&self) -> Result<String> {
+    fn value(&self) -> BuildResult<String> {
         Ok(self.paths.join(" "))
     }
 }
 
+/// Builder struct for [`VOLUME`] instruction (JSON form)
+///
+/// Format according to [Dockerfile
+/// reference](https://docs.docker.com/engine/reference/builder/#volume):
+/// * `VOLUME ["<path>", ...]`
+///
+/// Paths must not be empty, and are escaped to valid JSON strings.
+///
+/// Example:
+/// ```
+/// # use dockerfile_builder::instruction_builder::VolumeExecBuilder;
+/// let volume = VolumeExecBuilder::builder()
+///     .path("/my data")
+///     .path("/var/log")
+///     .build().unwrap();
+/// assert_eq!(volume.to_string(), r#"VOLUME ["/my data", "/var/log"]"#);
+/// ```
+///
+/// [VOLUME]: dockerfile_builder::instruction::VOLUME
+#[derive(Debug, InstructionBuilder)]
+#[instruction_builder(
+    instruction_name = VOLUME,
+    value_method = value,
+)]
+pub struct VolumeExecBuilder {
+    #[instruction_builder(each = path)]
+    pub paths: Vec<String>,
+}
+
+impl VolumeExecBuilder {
+    fn value(&self) -> BuildResult<String> {
+        if self.paths.is_empty() {
+            return Err(invalid!("VOLUME must have at least one path"));
+        }
+        if self.paths.iter().any(|p| p.trim().is_empty()) {
+            return Err(invalid!("VOLUME paths cannot be empty"));
+        }
+        let paths: Vec<_> = self.paths.iter().map(|p| json_string(p)).collect();
+        Ok(format!("[{}]", paths.join(", ")))
+    }
+}
+
 /// Builder struct for [`USER`] instruction
 ///
 /// Format according to [Dockerfile
 /// reference](https://docs.docker.com/engine/reference/builder/#user):
 /// * `USER <user>`
-/// or
+///   or
 /// * `USER <user>:<group>`
+///
 /// [USER]: dockerfile_builder::instruction::USER
 #[derive(Debug, InstructionBuilder)]
 #[instruction_builder(
@@ -846,7 +1975,7 @@ pub struct UserBuilder {
 }
 
 impl UserBuilder {
-    fn value(&self) -> Result<String> {
+    fn value(&self) -> BuildResult<String> {
         Ok(format!(
             "{}{}",
             self.user,
@@ -858,12 +1987,74 @@ impl UserBuilder {
     }
 }
 
+/// Builder struct for the deprecated [`MAINTAINER`] instruction
+///
+/// Format according to [Dockerfile
+/// reference](https://docs.docker.com/engine/reference/builder/#maintainer-deprecated):
+/// * `MAINTAINER <name>`
+///
+/// Deprecated by Docker: only use it to model legacy Dockerfiles. New Dockerfiles should set
+/// `LABEL maintainer=<name>` with [`LabelBuilder`] or
+/// [`Dockerfile::maintainer`](crate::Dockerfile::maintainer) instead.
+///
+/// Example:
+/// ```
+/// # use dockerfile_builder::instruction_builder::MaintainerBuilder;
+/// let maintainer = MaintainerBuilder::builder()
+///     .name("Jane Doe <jane@example.com>")
+///     .build()
+///     .unwrap();
+/// assert_eq!(maintainer.to_string(), "MAINTAINER Jane Doe <jane@example.com>");
+/// ```
+///
+/// [MAINTAINER]: dockerfile_builder::instruction::MAINTAINER
+#[derive(Debug, InstructionBuilder)]
+#[instruction_builder(
+    instruction_name = MAINTAINER,
+    value_method = value,
+)]
+pub struct MaintainerBuilder {
+    pub name: String,
+}
+
+impl MaintainerBuilder {
+    fn value(&self) -> BuildResult<String> {
+        if self.name.trim().is_empty() {
+            return Err(invalid!("MAINTAINER name cannot be empty"));
+        }
+        Ok(self.name.clone())
+    }
+}
+
 /// Builder struct for [`WORKDIR`] instruction
 ///
 /// Format according to [Dockerfile
 /// reference](https://docs.docker.com/engine/reference/builder/#workdir):
 /// * `WORKDIR <path>`
 ///
+/// Relative paths are resolved against the previous `WORKDIR`. Set `require_absolute` to reject
+/// them, the accepted prefixes are:
+/// * `/`, e.g. `/app`
+/// * a Windows drive letter, e.g. `C:\app` or `C:/app`
+/// * a variable reference, e.g. `$HOME` or `${APP_DIR}`
+///
+/// Example:
+/// ```
+/// # use dockerfile_builder::instruction_builder::WorkdirBuilder;
+/// let workdir = WorkdirBuilder::builder()
+///     .path("$HOME/app")
+///     .require_absolute(true)
+///     .build()
+///     .unwrap();
+/// assert_eq!(workdir.to_string(), "WORKDIR $HOME/app");
+///
+/// let workdir = WorkdirBuilder::builder()
+///     .path("app")
+///     .require_absolute(true)
+///     .build();
+/// assert!(workdir.is_err());
+/// ```
+///
 /// [WORKDIR]: dockerfile_builder::instruction::WORKDIR
 #[derive(Debug, InstructionBuilder)]
 #[instruction_builder(
@@ -872,20 +2063,76 @@ impl UserBuilder {
 )]
 pub struct WorkdirBuilder {
     pub path: String,
+    pub require_absolute: Option<bool>,
 }
 
 impl WorkdirBuilder {
-    fn value(&self) -> Result<String> {
+    fn value(&self) -> BuildResult<String> {
+        if self.require_absolute == Some(true) && !is_absolute_path(&self.path) {
+            return Err(invalid!("WORKDIR path must be absolute, got {}", self.path));
+        }
         Ok(self.path.to_string())
     }
 }
 
+/// Returns `true` if `path` starts with `/`, a Windows drive letter or a variable reference
+fn is_absolute_path(path: &str) -> bool {
+    let mut chars = path.chars();
+    let drive = matches!(
+        (chars.next(), chars.next(), chars.next()),
+        (Some(letter), Some(':'), Some('\\' | '/')) if letter.is_ascii_alphabetic()
+    );
+    path.starts_with('/') || path.starts_with('$') || drive
+}
+
 /// Builder struct for [`ARG`] instruction
 ///
 /// Format according to [Dockerfile
 /// reference](https://docs.docker.com/engine/reference/builder/#arg):
 /// * `ARG <name>[=<value>]`
 ///
+/// An empty value is rendered as `<name>=""`, set `bare_empty` to render it as `<name>=`. Set
+/// `normalize_name` to uppercase the name.
+///
+/// `choices` restricts the value to a fixed set. They aren't rendered, add the `ARG` with
+/// [`Dockerfile::push_arg`](crate::Dockerfile::push_arg) to return them from
+/// [`Dockerfile::build_args`](crate::Dockerfile::build_args). They're lost when the Dockerfile
+/// is rendered and parsed back.
+///
+/// Example:
+/// ```
+/// # use dockerfile_builder::instruction_builder::ArgBuilder;
+/// let arg = ArgBuilder::builder()
+///     .name("VERSION")
+///     .value("1.0")
+///     .build()
+///     .unwrap();
+/// assert_eq!(arg.to_string(), "ARG VERSION=1.0");
+///
+/// let arg = ArgBuilder::builder()
+///     .name("SUFFIX")
+///     .value("")
+///     .bare_empty(true)
+///     .build()
+///     .unwrap();
+/// assert_eq!(arg.to_string(), "ARG SUFFIX=");
+///
+/// let arg = ArgBuilder::builder()
+///     .name("version")
+///     .normalize_name(true)
+///     .build()
+///     .unwrap();
+/// assert_eq!(arg.to_string(), "ARG VERSION");
+///
+/// let arg = ArgBuilder::builder()
+///     .name("BUILD_MODE")
+///     .value("release")
+///     .choices(vec!["debug", "release"])
+///     .build()
+///     .unwrap();
+/// assert_eq!(arg.to_string(), "ARG BUILD_MODE=release");
+/// ```
+///
 /// [ARG]: dockerfile_builder::instruction::ARG
 #[derive(Debug, InstructionBuilder)]
 #[instruction_builder(
@@ -895,24 +2142,160 @@ impl WorkdirBuilder {
 pub struct ArgBuilder {
     pub name: String,
     pub value: Option<String>,
+    pub bare_empty: Option<bool>,
+    pub normalize_name: Option<bool>,
+    pub choices: Option<Vec<String>>,
 }
 
 impl ArgBuilder {
-    fn value(&self) -> Result<String> {
+    fn value(&self) -> BuildResult<String> {
+        let name = normalize_name(&self.name, self.normalize_name);
+        if let (Some(choices), Some(value)) = (&self.choices, &self.value) {
+            if !choices.contains(value) {
+                return Err(invalid!(
+                    "ARG {} value {} is not one of {}",
+                    name,
+                    value,
+                    choices.join("|")
+                ));
+            }
+        }
         let value = match &self.value {
-            Some(value) => format!("{}={}", self.name, value),
-            None => self.name.to_string(),
+            Some(value) if value.is_empty() => {
+                format!("{}={}", name, empty_value(self.bare_empty))
+            }
+            Some(value) => format!("{}={}", name, value),
+            None => name,
         };
         Ok(value)
     }
 }
 
+impl ArgBuilder {
+    /// Starts building an `ARG` declaring a predefined build argument, which doesn't need a
+    /// value
+    ///
+    /// Example:
+    /// ```
+    /// # use dockerfile_builder::instruction_builder::{ArgBuilder, PredefinedArg};
+    /// let arg = ArgBuilder::predefined(PredefinedArg::TargetPlatform)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(arg.to_string(), "ARG TARGETPLATFORM");
+    /// ```
+    pub fn predefined(arg: PredefinedArg) -> ArgBuilderInner {
+        let mut builder = ArgBuilder::builder();
+        builder.name(arg.to_string());
+        builder
+    }
+}
+
+impl ArgBuilderInner {
+    /// Returns the choices set with `choices()`
+    pub(crate) fn allowed_choices(&self) -> Option<&Vec<String>> {
+        self.choices.as_ref()
+    }
+}
+
+/// Predefined build arguments, see [`ArgBuilder::predefined`]
+///
+/// * Proxy arguments, set with `docker build --build-arg`.
+/// * Platform arguments, set automatically by BuildKit. They're only in scope after an `ARG`
+///   declaring them.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PredefinedArg {
+    HttpProxy,
+    HttpsProxy,
+    FtpProxy,
+    NoProxy,
+    AllProxy,
+    TargetPlatform,
+    TargetOs,
+    TargetArch,
+    TargetVariant,
+    BuildPlatform,
+    BuildOs,
+    BuildArch,
+    BuildVariant,
+}
+
+impl PredefinedArg {
+    /// All the predefined build arguments
+    pub const ALL: [PredefinedArg; 13] = [
+        PredefinedArg::HttpProxy,
+        PredefinedArg::HttpsProxy,
+        PredefinedArg::FtpProxy,
+        PredefinedArg::NoProxy,
+        PredefinedArg::AllProxy,
+        PredefinedArg::TargetPlatform,
+        PredefinedArg::TargetOs,
+        PredefinedArg::TargetArch,
+        PredefinedArg::TargetVariant,
+        PredefinedArg::BuildPlatform,
+        PredefinedArg::BuildOs,
+        PredefinedArg::BuildArch,
+        PredefinedArg::BuildVariant,
+    ];
+
+    /// Returns the predefined build argument named `name`, if any
+    ///
+    /// Proxy arguments are also recognized in lowercase, e.g. `http_proxy`.
+    pub fn from_name(name: &str) -> Option<PredefinedArg> {
+        PredefinedArg::ALL.into_iter().find(|arg| {
+            let predefined = arg.to_string();
+            predefined == name
+                || (predefined.ends_with("_PROXY") && predefined.to_lowercase() == name)
+        })
+    }
+}
+
+impl Display for PredefinedArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PredefinedArg::HttpProxy => "HTTP_PROXY",
+            PredefinedArg::HttpsProxy => "HTTPS_PROXY",
+            PredefinedArg::FtpProxy => "FTP_PROXY",
+            PredefinedArg::NoProxy => "NO_PROXY",
+            PredefinedArg::AllProxy => "ALL_PROXY",
+            PredefinedArg::TargetPlatform => "TARGETPLATFORM",
+            PredefinedArg::TargetOs => "TARGETOS",
+            PredefinedArg::TargetArch => "TARGETARCH",
+            PredefinedArg::TargetVariant => "TARGETVARIANT",
+            PredefinedArg::BuildPlatform => "BUILDPLATFORM",
+            PredefinedArg::BuildOs => "BUILDOS",
+            PredefinedArg::BuildArch => "BUILDARCH",
+            PredefinedArg::BuildVariant => "BUILDVARIANT",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 /// Builder struct for [`ONBUILD`] instruction
 ///
 /// Format according to [Dockerfile
 /// reference](https://docs.docker.com/engine/reference/builder/#onbuild):
 /// * `ONBUILD <INSTRUCTION>`
 ///
+/// `instruction` accepts anything convertible into an [`Instruction`], e.g. a `RUN` or a
+/// built `COPY`. The comment of the instruction isn't rendered. `ONBUILD ONBUILD`,
+/// `ONBUILD FROM`, `ONBUILD MAINTAINER` and comments aren't allowed.
+///
+/// Example:
+/// ```
+/// # use dockerfile_builder::instruction::RUN;
+/// # use dockerfile_builder::instruction_builder::{CopyBuilder, OnbuildBuilder};
+/// let onbuild = OnbuildBuilder::builder()
+///     .instruction(RUN::from("make install"))
+///     .build().unwrap();
+/// assert_eq!(onbuild.to_string(), "ONBUILD RUN make install");
+///
+/// let copy = CopyBuilder::builder().src(".").dest("/app/src").build().unwrap();
+/// let onbuild = OnbuildBuilder::builder()
+///     .instruction(copy)
+///     .build().unwrap();
+/// assert_eq!(onbuild.to_string(), "ONBUILD COPY . /app/src");
+/// ```
+///
 /// [ONBUILD]: dockerfile_builder::instruction::ONBUILD
 #[derive(Debug, InstructionBuilder)]
 #[instruction_builder(
@@ -924,15 +2307,27 @@ pub struct OnbuildBuilder {
 }
 
 impl OnbuildBuilder {
-    fn value(&self) -> Result<String> {
+    fn value(&self) -> BuildResult<String> {
         match &self.instruction {
-            Instruction::ONBUILD(_) => Err(eyre!(
+            Instruction::ONBUILD(_) => Err(invalid!(
                 "Chaining ONBUILD instructions using ONBUILD ONBUILD isn’t allowed"
             )),
-            Instruction::FROM(_) => Err(eyre!(
+            Instruction::FROM(_) => Err(invalid!(
                 "ONBUILD instruction may not trigger FROM instruction"
             )),
-            ins => Ok(ins.to_string()),
+            Instruction::MAINTAINER(_) => Err(invalid!(
+                "ONBUILD instruction may not trigger MAINTAINER instruction"
+            )),
+            Instruction::COMMENT(_) => {
+                Err(invalid!("ONBUILD instruction may not trigger a comment"))
+            }
+            ins => {
+                let mut ins = ins.clone();
+                if let Some(comment) = ins.comment_mut() {
+                    *comment = None;
+                }
+                Ok(ins.to_string())
+            }
         }
     }
 }
@@ -943,6 +2338,27 @@ impl OnbuildBuilder {
 /// reference](https://docs.docker.com/engine/reference/builder/#stopsignal):
 /// * `STOPSIGNAL <signal>`
 ///
+/// Example:
+/// ```
+/// # use dockerfile_builder::instruction_builder::{Signal, StopsignalBuilder};
+/// let stopsignal = StopsignalBuilder::builder()
+///     .signal(Signal::Term)
+///     .build().unwrap();
+/// assert_eq!(stopsignal.to_string(), "STOPSIGNAL SIGTERM");
+///
+/// // build STOPSIGNAL with a signal number
+/// let stopsignal = StopsignalBuilder::builder()
+///     .signal(Signal::Number(9))
+///     .build().unwrap();
+/// assert_eq!(stopsignal.to_string(), "STOPSIGNAL 9");
+///
+/// // build STOPSIGNAL with any signal name
+/// let stopsignal = StopsignalBuilder::builder()
+///     .signal_name("SIGRTMIN+3")
+///     .build().unwrap();
+/// assert_eq!(stopsignal.to_string(), "STOPSIGNAL SIGRTMIN+3");
+/// ```
+///
 /// [STOPSIGNAL]: dockerfile_builder::instruction::STOPSIGNAL
 #[derive(Debug, InstructionBuilder)]
 #[instruction_builder(
@@ -950,21 +2366,122 @@ impl OnbuildBuilder {
     value_method = value,
 )]
 pub struct StopsignalBuilder {
-    pub signal: String,
+    pub signal: Signal,
 }
 
 impl StopsignalBuilder {
-    fn value(&self) -> Result<String> {
-        Ok(self.signal.to_string())
+    fn value(&self) -> BuildResult<String> {
+        match &self.signal {
+            Signal::Number(n) if !(1..=64).contains(n) => Err(invalid!(
+                "STOPSIGNAL number must be between 1 and 64, got {}",
+                n
+            )),
+            Signal::Custom(name) if name.trim().is_empty() => {
+                Err(invalid!("STOPSIGNAL signal name cannot be empty"))
+            }
+            signal => Ok(signal.to_string()),
+        }
     }
 }
 
-/// Builder struct for [`HEALTHCHECK`] instruction
-///
-/// Format according to [Dockerfile
-/// reference](https://docs.docker.com/engine/reference/builder/#healthcheck):
-/// * `HEALTHCHECK [--interval=DURATION] [--timeout=DURATION]
-///                [--start-period=DURATION] [--retries=N] CMD <command>`
+impl StopsignalBuilderInner {
+    /// Sets the signal by name, e.g. `SIGRTMIN+3`, without checking it
+    pub fn signal_name<T: Into<String>>(&mut self, name: T) -> &mut Self {
+        self.signal(Signal::Custom(name.into()))
+    }
+}
+
+/// Signal for [`StopsignalBuilder`]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Signal {
+    /// `SIGHUP`
+    Hup,
+    /// `SIGINT`
+    Int,
+    /// `SIGQUIT`
+    Quit,
+    /// `SIGABRT`
+    Abrt,
+    /// `SIGKILL`
+    Kill,
+    /// `SIGUSR1`
+    Usr1,
+    /// `SIGUSR2`
+    Usr2,
+    /// `SIGPIPE`
+    Pipe,
+    /// `SIGALRM`
+    Alrm,
+    /// `SIGTERM`
+    Term,
+    /// `SIGCONT`
+    Cont,
+    /// `SIGSTOP`
+    Stop,
+    /// `SIGWINCH`
+    Winch,
+    /// Signal number, between 1 and 64
+    Number(u8),
+    /// Any other signal name
+    Custom(String),
+}
+
+impl Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Signal::Hup => "SIGHUP",
+            Signal::Int => "SIGINT",
+            Signal::Quit => "SIGQUIT",
+            Signal::Abrt => "SIGABRT",
+            Signal::Kill => "SIGKILL",
+            Signal::Usr1 => "SIGUSR1",
+            Signal::Usr2 => "SIGUSR2",
+            Signal::Pipe => "SIGPIPE",
+            Signal::Alrm => "SIGALRM",
+            Signal::Term => "SIGTERM",
+            Signal::Cont => "SIGCONT",
+            Signal::Stop => "SIGSTOP",
+            Signal::Winch => "SIGWINCH",
+            Signal::Number(n) => return write!(f, "{}", n),
+            Signal::Custom(name) => name,
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Builder struct for [`HEALTHCHECK`] instruction
+///
+/// Format according to [Dockerfile
+/// reference](https://docs.docker.com/engine/reference/builder/#healthcheck):
+/// * `HEALTHCHECK [--interval=DURATION] [--timeout=DURATION] [--start-period=DURATION]
+///                [--start-interval=DURATION] [--retries=N] CMD <command>`
+///   or
+/// * `HEALTHCHECK NONE`
+///
+/// Durations are rendered in the Docker duration format, e.g. `30s` or `1m30s`. Flags are
+/// always rendered in the order above.
+///
+/// Example:
+/// ```
+/// # use dockerfile_builder::instruction::CMD;
+/// # use dockerfile_builder::instruction_builder::HealthcheckBuilder;
+/// # use std::time::Duration;
+/// let healthcheck = HealthcheckBuilder::builder()
+///     .cmd(CMD::from("curl -f http://localhost/"))
+///     .interval(Duration::from_secs(30))
+///     .timeout(Duration::from_secs(90))
+///     .build().unwrap();
+/// assert_eq!(
+///     healthcheck.to_string(),
+///     "HEALTHCHECK --interval=30s --timeout=1m30s CMD curl -f http://localhost/",
+/// );
+///
+/// // disable any healthcheck inherited from the base image
+/// let healthcheck = HealthcheckBuilder::builder()
+///     .none(true)
+///     .build().unwrap();
+/// assert_eq!(healthcheck.to_string(), "HEALTHCHECK NONE");
+/// ```
 ///
 /// [HEALTHCHECK]: dockerfile_builder::instruction::HEALTHCHECK
 #[derive(Debug, InstructionBuilder)]
@@ -973,35 +2490,72 @@ impl StopsignalBuilder {
     value_method = value,
 )]
 pub struct HealthcheckBuilder {
-    pub cmd: CMD,
-    pub interval: Option<i32>,
-    pub timeout: Option<i32>,
-    pub start_period: Option<i32>,
+    pub cmd: Option<CMD>,
+    pub interval: Option<Duration>,
+    pub timeout: Option<Duration>,
+    pub start_period: Option<Duration>,
+    pub start_interval: Option<Duration>,
     pub retries: Option<i32>,
+    pub none: Option<bool>,
 }
 
 impl HealthcheckBuilder {
-    fn value(&self) -> Result<String> {
-        Ok(format!(
-            "{}{}{}{}{}",
-            self.interval
-                .as_ref()
-                .map(|i| format!("--interal={} ", i))
-                .unwrap_or_default(),
-            self.timeout
-                .as_ref()
-                .map(|t| format!("--timeout={} ", t))
-                .unwrap_or_default(),
-            self.start_period
-                .as_ref()
-                .map(|s| format!("--start-period={} ", s))
-                .unwrap_or_default(),
-            self.retries
-                .as_ref()
-                .map(|r| format!("--retries={} ", r))
-                .unwrap_or_default(),
-            self.cmd,
-        ))
+    fn value(&self) -> BuildResult<String> {
+        if self.none == Some(true) {
+            let has_options = self.cmd.is_some()
+                || self.interval.is_some()
+                || self.timeout.is_some()
+                || self.start_period.is_some()
+                || self.start_interval.is_some()
+                || self.retries.is_some();
+            if has_options {
+                return Err(invalid!(
+                    "HEALTHCHECK NONE cannot be combined with a command or options"
+                ));
+            }
+            return Ok("NONE".to_string());
+        }
+
+        let cmd = self.cmd.as_ref().ok_or(invalid!(
+            "cmd is required for HealthcheckBuilder unless none is set"
+        ))?;
+        let durations = [
+            ("interval", self.interval),
+            ("timeout", self.timeout),
+            ("start-period", self.start_period),
+            ("start-interval", self.start_interval),
+        ];
+        let mut flags = durations
+            .iter()
+            .filter_map(|(name, d)| d.map(|d| format!("--{}={}", name, format_duration(d))))
+            .collect::<Vec<_>>();
+        flags.extend(self.retries.map(|r| format!("--retries={}", r)));
+        flags.push(cmd.to_string());
+        Ok(flags.join(" "))
+    }
+}
+
+/// Formats a duration in the Docker duration format, e.g. `1h2m3s` or `500ms`
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let nanos = duration.subsec_nanos() as u64;
+    let units = [
+        (secs / 3600, "h"),
+        (secs % 3600 / 60, "m"),
+        (secs % 60, "s"),
+        (nanos / 1_000_000, "ms"),
+        (nanos / 1_000 % 1_000, "us"),
+        (nanos % 1_000, "ns"),
+    ];
+    let formatted = units
+        .iter()
+        .filter(|(amount, _)| *amount > 0)
+        .map(|(amount, unit)| format!("{}{}", amount, unit))
+        .collect::<String>();
+    if formatted.is_empty() {
+        "0s".to_string()
+    } else {
+        formatted
     }
 }
 
@@ -1028,6 +2582,12 @@ impl HealthcheckBuilder {
 ///     .params(vec!["/S", "/C"])
 ///     .build().unwrap();
 /// assert_eq!(shell.to_string(), r#"SHELL ["cmd", "/S", "/C"]"#);
+///
+/// // executable and params are escaped as JSON strings
+/// let shell = ShellBuilder::builder()
+///     .executable(r"C:\Windows\System32\cmd.exe")
+///     .build().unwrap();
+/// assert_eq!(shell.to_string(), r#"SHELL ["C:\\Windows\\System32\\cmd.exe"]"#);
 /// ```
 ///
 /// [SHELL]: dockerfile_builder::instruction::SHELL
@@ -1043,84 +2603,833 @@ pub struct ShellBuilder {
 }
 
 impl ShellBuilder {
-    fn value(&self) -> Result<String> {
-        let params = match self.params.clone() {
-            Some(param_vec) => {
-                if param_vec.is_empty() {
-                    String::new()
-                } else {
-                    format!(r#", "{}""#, param_vec.join(r#"", ""#))
-                }
+    fn value(&self) -> BuildResult<String> {
+        Ok(exec_array(Some(&self.executable), &self.params))
+    }
+}
+
+/// Quotes a value as a JSON string for the exec form
+pub(crate) fn json_string(value: &str) -> String {
+    let mut json = String::from('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str(r#"\""#),
+            '\\' => json.push_str(r"\\"),
+            '\n' => json.push_str(r"\n"),
+            '\r' => json.push_str(r"\r"),
+            '\t' => json.push_str(r"\t"),
+            c if c.is_control() => json.push_str(&format!(r"\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Returns the single `key`/`value` pair followed by `pairs`, of at least one pair
+fn key_value_pairs<'a>(
+    instruction: &str,
+    key: &'a Option<String>,
+    value: &'a Option<String>,
+    pairs: &'a Option<Vec<(String, String)>>,
+) -> BuildResult<Vec<(&'a str, &'a str)>> {
+    let pair = match (key, value) {
+        (Some(key), Some(value)) => Some((key.as_str(), value.as_str())),
+        (None, None) => None,
+        _ => {
+            return Err(invalid!(
+                "{} key and value must be set together",
+                instruction
+            ))
+        }
+    };
+    let pairs = pair
+        .into_iter()
+        .chain(
+            pairs
+                .iter()
+                .flatten()
+                .map(|(k, v)| (k.as_str(), v.as_str())),
+        )
+        .collect::<Vec<_>>();
+    if pairs.is_empty() {
+        return Err(invalid!(
+            "{} requires at least one key/value pair",
+            instruction
+        ));
+    }
+    Ok(pairs)
+}
+
+/// Renders the key/value pairs of `LABEL` and `ENV` with `render`, separated by spaces
+fn render_pairs<F: Fn(&str, &str) -> String>(pairs: Vec<(&str, &str)>, render: F) -> String {
+    pairs
+        .into_iter()
+        .map(|(key, value)| render(key, value))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Wraps a key-value value in double quotes if it contains whitespace, `=`, quotes or backslashes.
+/// Backslashes, double quotes and newlines are escaped inside the quotes.
+pub(crate) fn quote_value(value: &str) -> String {
+    let needs_quotes =
+        value.contains(|c: char| c.is_whitespace() || matches!(c, '=' | '"' | '\'' | '\\'));
+    if !needs_quotes {
+        return value.to_string();
+    }
+    let escaped = value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n");
+    format!(r#""{}""#, escaped)
+}
+
+/// Renders the `--chmod` flag of `ADD` and `COPY`, followed by a space
+///
+/// `chmod_str` must be an octal mode of 3 or 4 digits and is rendered as is.
+fn chmod_flag(chmod: Option<u16>, chmod_str: &Option<String>) -> BuildResult<String> {
+    match (chmod, chmod_str) {
+        (Some(_), Some(_)) => Err(invalid!("chmod and chmod_str cannot both be set")),
+        (Some(mode), None) => Ok(format!("--chmod={} ", mode)),
+        (None, Some(mode)) => {
+            let is_octal =
+                (3..=4).contains(&mode.len()) && mode.chars().all(|c| matches!(c, '0'..='7'));
+            if !is_octal {
+                return Err(invalid!(
+                    "chmod must be an octal mode like 0755, got {}",
+                    mode
+                ));
             }
-            None => String::new(),
-        };
-        Ok(format!(r#"["{}"{}]"#, self.executable, params))
+            Ok(format!("--chmod={} ", mode))
+        }
+        (None, None) => Ok(String::new()),
+    }
+}
+
+/// Renders the JSON array of an exec form instruction
+///
+/// `None` and `Some(vec![])` params both render only the executable, e.g. `["exe"]`. Every
+/// element is escaped with [`json_string`].
+fn exec_array(executable: Option<&str>, params: &Option<Vec<String>>) -> String {
+    let words = executable
+        .into_iter()
+        .chain(params.iter().flatten().map(String::as_str))
+        .map(json_string)
+        .collect::<Vec<_>>();
+    format!("[{}]", words.join(", "))
+}
+
+/// Renders an empty value as `""`, or as nothing if `bare_empty` is set
+fn empty_value(bare_empty: Option<bool>) -> String {
+    match bare_empty {
+        Some(true) => String::new(),
+        _ => r#""""#.to_string(),
+    }
+}
+
+/// Returns the name uppercased if `normalize` is set
+fn normalize_name(name: &str, normalize: Option<bool>) -> String {
+    match normalize {
+        Some(true) => name.to_uppercase(),
+        _ => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expect_test::expect;
+
+    #[test]
+    fn from() {
+        let from = FromBuilder::builder().image("cargo-chef").build().unwrap();
+        let expected = expect!["FROM cargo-chef"];
+        expected.assert_eq(&from.to_string());
+
+        let from = FromBuilder::builder()
+            .image("cargo-chef")
+            .platform(Platform::LinuxArm64)
+            .build()
+            .unwrap();
+        let expected = expect!["FROM --platform=linux/arm64 cargo-chef"];
+        expected.assert_eq(&from.to_string());
+
+        let from = FromBuilder::builder()
+            .image("cargo-chef")
+            .name("chef")
+            .tag("latest")
+            .build()
+            .unwrap();
+        let expected = expect!["FROM cargo-chef:latest AS chef"];
+        expected.assert_eq(&from.to_string());
+
+        let from = FromBuilder::builder()
+            .image("cargo-chef")
+            .name("chef")
+            .digest("sha256")
+            .build()
+            .unwrap();
+        let expected = expect!["FROM cargo-chef@sha256 AS chef"];
+        expected.assert_eq(&from.to_string());
+    }
+
+    #[test]
+    fn from_stage() {
+        let from = FromBuilder::from_stage("builder").build().unwrap();
+        let expected = expect!["FROM builder"];
+        expected.assert_eq(&from.to_string());
+
+        let from = FromBuilder::from_stage("builder")
+            .platform(Platform::LinuxAmd64)
+            .build();
+        assert!(from.is_err());
+
+        let from = FromBuilder::from_stage("builder").tag("latest").build();
+        assert!(from.is_err());
+
+        let from = FromBuilder::builder()
+            .image("rust")
+            .platform("${BUILDPLATFORM}".parse().unwrap())
+            .build()
+            .unwrap();
+        let expected = expect!["FROM --platform=${BUILDPLATFORM} rust"];
+        expected.assert_eq(&from.to_string());
+    }
+
+    #[test]
+    fn builder_error() {
+        let err = FromBuilder::builder().name("builder").build().unwrap_err();
+        assert_eq!(
+            err,
+            BuilderError::MissingField {
+                field: "image",
+                builder: "FromBuilder",
+            }
+        );
+        assert_eq!(err.to_string(), "image is required for FromBuilder");
+
+        let result: BuildResult<FROM> = FromBuilder::builder()
+            .image("rust")
+            .tag("latest")
+            .digest("sha256:abc")
+            .build();
+        assert_eq!(
+            result,
+            Err(BuilderError::Invalid(
+                "Dockerfile image can only have tag OR digest".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn from_comment() {
+        let from = FromBuilder::builder()
+            .comment("---- builder stage ----\nCompiles the app")
+            .image("rust")
+            .tag("1.70")
+            .name("builder")
+            .build()
+            .unwrap();
+        let expected = expect![[r#"
+            # ---- builder stage ----
+            # Compiles the app
+            FROM rust:1.70 AS builder"#]];
+        expected.assert_eq(&from.to_string());
+    }
+
+    #[test]
+    fn run() {
+        let run = RunExecBuilder::builder()
+            .executable("mybin.exe")
+            .build()
+            .unwrap();
+        let expected = expect![r#"RUN ["mybin.exe"]"#];
+        expected.assert_eq(&run.to_string());
+    }
+
+    #[test]
+    fn run_separator() {
+        let commands = vec!["apt-get update", "apt-get install -y curl"];
+        let rendered = [
+            Separator::AndAnd,
+            Separator::Semicolon,
+            Separator::SingleLine,
+        ]
+        .map(|separator| {
+            RunBuilder::builder()
+                .commands(commands.clone())
+                .separator(separator)
+                .build()
+                .unwrap()
+                .to_string()
+        })
+        .join("\n");
+        let expected = expect![[r#"
+            RUN apt-get update && \
+                apt-get install -y curl
+            RUN apt-get update; \
+                apt-get install -y curl
+            RUN apt-get update && apt-get install -y curl"#]];
+        expected.assert_eq(&rendered);
+
+        let run = RunBuilder::builder()
+            .command("cat <<EOF > /x\nhi\nEOF")
+            .command("cat /x")
+            .separator(Separator::AndAnd)
+            .build()
+            .unwrap();
+        let expected = expect![[r#"
+            RUN cat <<EOF > /x && cat /x
+            hi
+            EOF"#]];
+        expected.assert_eq(&run.to_string());
+    }
+
+    #[test]
+    fn run_wrap_at() {
+        let packages = "apt-get install -y --no-install-recommends ca-certificates curl git";
+        let run = RunBuilder::builder()
+            .command("apt-get update")
+            .command(packages)
+            .command(r#"echo "a quoted string that is not wrapped" > /done"#)
+            .separator(Separator::AndAnd)
+            .wrap_at(40)
+            .build()
+            .unwrap();
+        let expected = expect![[r#"
+            RUN apt-get update && \
+                apt-get install -y \
+                --no-install-recommends \
+                ca-certificates curl git && \
+                echo \
+                "a quoted string that is not wrapped" \
+                > /done"#]];
+        expected.assert_eq(&run.to_string());
+        assert!(run
+            .to_string()
+            .lines()
+            .filter(|line| !line.contains('"'))
+            .all(|line| line.len() <= 40));
+
+        // Short lines and unset width are left as is
+        let run = RunBuilder::builder()
+            .command(packages)
+            .wrap_at(100)
+            .build()
+            .unwrap();
+        assert_eq!(run.to_string(), format!("RUN {}", packages));
+        let run = RunBuilder::builder().command(packages).build().unwrap();
+        assert_eq!(run.to_string(), format!("RUN {}", packages));
+
+        // Heredocs are not wrapped
+        let run = RunBuilder::builder()
+            .command("cat <<EOF > /a/long/path/to/the/file\nhi\nEOF")
+            .wrap_at(10)
+            .build()
+            .unwrap();
+        let expected = expect![[r#"
+            RUN cat <<EOF > /a/long/path/to/the/file
+            hi
+            EOF"#]];
+        expected.assert_eq(&run.to_string());
+    }
+
+    #[test]
+    fn run_exec_shell() {
+        let run = RunBuilder::builder()
+            .exec_shell(vec!["/bin/bash", "-c"])
+            .mount(Mount::Cache(CacheMount {
+                target: "/root/.cache".to_string(),
+                ..Default::default()
+            }))
+            .commands(vec!["apt-get update", "echo \"done\""])
+            .build()
+            .unwrap();
+        let expected = expect![[
+            r#"RUN --mount=type=cache,target=/root/.cache ["/bin/bash", "-c", "apt-get update && echo \"done\""]"#
+        ]];
+        expected.assert_eq(&run.to_string());
+
+        let err = RunBuilder::builder()
+            .exec_shell(vec!["/bin/bash", "-c"])
+            .command("cat <<EOF > /x\nhi\nEOF")
+            .build()
+            .unwrap_err();
+        let expected = expect!["heredocs cannot be used with exec_shell"];
+        expected.assert_eq(&err.to_string());
+    }
+
+    #[test]
+    fn env() {
+        let env = EnvBuilder::builder()
+            .key("CONFIG")
+            .value("[server]\nname = \"app\"")
+            .build()
+            .unwrap();
+        let expected = expect![[r#"ENV CONFIG="[server]\nname = \"app\"""#]];
+        expected.assert_eq(&env.to_string());
+    }
+
+    #[test]
+    fn env_legacy_form() {
+        let env = EnvBuilder::builder()
+            .env("JAVA_OPTS", "-Xmx1g -Dfile.encoding=UTF-8")
+            .legacy_form(true)
+            .build()
+            .unwrap();
+        let expected = expect!["ENV JAVA_OPTS -Xmx1g -Dfile.encoding=UTF-8"];
+        expected.assert_eq(&env.to_string());
+
+        let err = EnvBuilder::builder()
+            .env("A", "1")
+            .env("B", "2")
+            .legacy_form(true)
+            .build()
+            .unwrap_err();
+        let expected = expect!["ENV legacy form only supports a single key/value pair"];
+        expected.assert_eq(&err.to_string());
+
+        let err = EnvBuilder::builder()
+            .key("A")
+            .value("")
+            .legacy_form(true)
+            .build()
+            .unwrap_err();
+        let expected = expect!["ENV legacy form requires a non-empty, single line value"];
+        expected.assert_eq(&err.to_string());
+    }
+
+    #[test]
+    fn env_multiple() {
+        let env = EnvBuilder::builder()
+            .env("A", "1")
+            .env("B", "2")
+            .env("GREETING", "hello world")
+            .build()
+            .unwrap();
+        let expected = expect![[r#"ENV A=1 B=2 GREETING="hello world""#]];
+        expected.assert_eq(&env.to_string());
+
+        let env = EnvBuilderInner::from(vec![("B", "2"), ("A", "1")])
+            .build()
+            .unwrap();
+        let expected = expect!["ENV B=2 A=1"];
+        expected.assert_eq(&env.to_string());
+
+        let env = EnvBuilder::from_map(BTreeMap::from([("B", "2"), ("A", "1")]))
+            .comment("from a map")
+            .build()
+            .unwrap();
+        let expected = expect![[r#"
+            # from a map
+            ENV A=1 B=2"#]];
+        expected.assert_eq(&env.to_string());
+
+        let env = EnvBuilderInner::from(HashMap::from([("A", "1")]))
+            .build()
+            .unwrap();
+        let expected = expect!["ENV A=1"];
+        expected.assert_eq(&env.to_string());
+    }
+
+    #[test]
+    fn env_err() {
+        let env = EnvBuilder::builder().value("bar").build();
+        match env {
+            Ok(_) => panic!("Value without key. Expect test to fail"),
+            Err(e) => assert_eq!(e.to_string(), "ENV key and value must be set together"),
+        }
+    }
+
+    #[test]
+    fn run_tmpfs_mount() {
+        let run = RunBuilder::builder()
+            .mount(Mount::Tmpfs(TmpfsMount {
+                target: "/tmp".to_string(),
+                size: None,
+            }))
+            .command("make")
+            .build()
+            .unwrap();
+        let expected = expect!["RUN --mount=type=tmpfs,target=/tmp make"];
+        expected.assert_eq(&run.to_string());
+
+        let run = RunBuilder::builder()
+            .mount(Mount::Tmpfs(TmpfsMount {
+                target: "/tmp".to_string(),
+                size: Some("100m".to_string()),
+            }))
+            .command("make")
+            .build()
+            .unwrap();
+        let expected = expect!["RUN --mount=type=tmpfs,target=/tmp,size=100m make"];
+        expected.assert_eq(&run.to_string());
+    }
+
+    #[test]
+    fn run_mounts() {
+        let run = RunBuilder::builder()
+            .mount(Mount::Cache(CacheMount {
+                target: "/root/.cargo".to_string(),
+                ..Default::default()
+            }))
+            .mount(Mount::Bind(BindMount {
+                target: "/src".to_string(),
+                source: Some("app".to_string()),
+                from: Some("builder".to_string()),
+                read_write: Some(true),
+            }))
+            .mount(Mount::Secret(SecretMount {
+                id: Some("aws".to_string()),
+                target: Some("/root/.aws/credentials".to_string()),
+                required: Some(true),
+            }))
+            .command("cargo build")
+            .build()
+            .unwrap();
+        let expected = expect!["RUN --mount=type=cache,target=/root/.cargo --mount=type=bind,target=/src,source=app,from=builder,rw=true --mount=type=secret,id=aws,target=/root/.aws/credentials,required=true cargo build"];
+        expected.assert_eq(&run.to_string());
+
+        let mount = Mount::Cache(CacheMount {
+            target: "/var/cache/apt".to_string(),
+            id: Some("apt".to_string()),
+            sharing: Some(Sharing::Private),
+            source: Some("cache".to_string()),
+            from: Some("base".to_string()),
+        });
+        let expected = expect![
+            "type=cache,target=/var/cache/apt,id=apt,sharing=private,source=cache,from=base"
+        ];
+        expected.assert_eq(&mount.to_string());
+    }
+
+    #[test]
+    fn run_network() {
+        let run = RunBuilder::builder()
+            .mount(Mount::Tmpfs(TmpfsMount {
+                target: "/tmp".to_string(),
+                size: None,
+            }))
+            .network(Network::Host)
+            .command("curl example.com")
+            .build()
+            .unwrap();
+        let expected =
+            expect!["RUN --mount=type=tmpfs,target=/tmp --network=host curl example.com"];
+        expected.assert_eq(&run.to_string());
+
+        let run = RunExecBuilder::builder()
+            .network(Network::Default)
+            .executable("cargo")
+            .param("fetch")
+            .build()
+            .unwrap();
+        let expected = expect![[r#"RUN --network=default ["cargo", "fetch"]"#]];
+        expected.assert_eq(&run.to_string());
+    }
+
+    #[test]
+    fn run_exec_sh_wrapped() {
+        let rendered = [true, false]
+            .map(|strict| {
+                RunExecBuilder::builder()
+                    .network(Network::None)
+                    .sh_wrapped(r#"make test && echo "done""#, strict)
+                    .build()
+                    .unwrap()
+                    .to_string()
+            })
+            .join("\n");
+        let expected = expect![[r#"
+            RUN --network=none ["/bin/sh", "-euc", "make test && echo \"done\""]
+            RUN --network=none ["/bin/sh", "-c", "make test && echo \"done\""]"#]];
+        expected.assert_eq(&rendered);
+
+        // Replaces the params set before
+        let run = RunExecBuilder::builder()
+            .executable("make")
+            .param("test")
+            .sh_wrapped("make test", false)
+            .build()
+            .unwrap();
+        let expected = expect![[r#"RUN ["/bin/sh", "-c", "make test"]"#]];
+        expected.assert_eq(&run.to_string());
+    }
+
+    #[test]
+    fn run_security() {
+        let run = RunBuilder::builder()
+            .network(Network::None)
+            .security(Security::Sandbox)
+            .command("make")
+            .build()
+            .unwrap();
+        let expected = expect!["RUN --network=none --security=sandbox make"];
+        expected.assert_eq(&run.to_string());
+    }
+
+    #[test]
+    fn run_script() {
+        let run = RunBuilder::builder()
+            .script(|s| {
+                s.cmd("source $HOME/.bashrc");
+                s.cmd("echo $HOME");
+            })
+            .build()
+            .unwrap();
+        let chained = RunBuilder::builder()
+            .command("source $HOME/.bashrc")
+            .command("echo $HOME")
+            .build()
+            .unwrap();
+        assert_eq!(run, chained);
+        let expected = expect!["RUN source $HOME/.bashrc && echo $HOME"];
+        expected.assert_eq(&run.to_string());
+    }
+
+    #[test]
+    fn run_write_file() {
+        let run = RunBuilder::builder()
+            .command("mkdir -p /etc/app")
+            .write_file("/etc/app/config", "a = 1\nb = \"$B\"\n")
+            .write_file("/etc/app/notes", "EOF\nEOF1")
+            .build()
+            .unwrap();
+        let expected = expect![[r#"
+            RUN mkdir -p /etc/app && cat <<'EOF' > /etc/app/config && cat <<'EOF2' > /etc/app/notes
+            a = 1
+            b = "$B"
+            EOF
+            EOF
+            EOF1
+            EOF2"#]];
+        expected.assert_eq(&run.to_string());
+
+        let run = RunBuilder::builder()
+            .write_file("/etc/my app/$NAME.conf", "a = 1")
+            .build()
+            .unwrap();
+        let expected = expect![[r#"
+            RUN cat <<'EOF' > '/etc/my app/$NAME.conf'
+            a = 1
+            EOF"#]];
+        expected.assert_eq(&run.to_string());
+    }
+
+    #[test]
+    fn run_heredoc() {
+        let run = RunHeredocBuilder::builder()
+            .delimiter("SCRIPT")
+            .lines(vec!["cd /app", "make"])
+            .build()
+            .unwrap();
+        let expected = expect![[r#"
+            RUN <<SCRIPT
+            cd /app
+            make
+            SCRIPT"#]];
+        expected.assert_eq(&run.to_string());
+
+        // The delimiter defaults to EOF
+        let builder = RunHeredocBuilder::builder()
+            .line("make")
+            .check_build()
+            .unwrap();
+        assert_eq!(builder.delimiter, "EOF");
+        let run = RunHeredocBuilder::builder().line("make").build().unwrap();
+        let expected = expect![[r#"
+            RUN <<EOF
+            make
+            EOF"#]];
+        expected.assert_eq(&run.to_string());
+    }
+
+    #[test]
+    fn run_heredoc_err() {
+        let run = RunHeredocBuilder::builder().build();
+        assert!(run.is_err());
+
+        let run = RunHeredocBuilder::builder()
+            .line("echo start")
+            .line("EOF")
+            .build();
+        assert!(run.is_err());
+    }
+
+    #[test]
+    fn shell_quote() {
+        assert_eq!(super::shell_quote("/usr/bin/app-1.0"), "/usr/bin/app-1.0");
+        assert_eq!(super::shell_quote(""), "''");
+        assert_eq!(super::shell_quote("a b"), "'a b'");
+        assert_eq!(super::shell_quote("$HOME"), "'$HOME'");
+        assert_eq!(super::shell_quote("don't"), r#"'don'"'"'t'"#);
+    }
+
+    #[test]
+    fn cmd() {
+        let cmd = CmdBuilder::builder()
+            .command("some command")
+            .build()
+            .unwrap();
+        let expected = expect!["CMD some command"];
+        expected.assert_eq(&cmd.to_string());
+
+        let cmd = CmdExecBuilder::builder()
+            .executable("some command")
+            .build()
+            .unwrap();
+        let expected = expect![r#"CMD ["some command"]"#];
+        expected.assert_eq(&cmd.to_string());
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use expect_test::expect;
 
     #[test]
-    fn from() {
-        let from = FromBuilder::builder().image("cargo-chef").build().unwrap();
-        let expected = expect!["FROM cargo-chef"];
-        expected.assert_eq(&from.to_string());
+    fn exec_empty_params() {
+        let no_params = Vec::<String>::new();
+        let run = RunExecBuilder::builder()
+            .executable("make")
+            .params(no_params.clone())
+            .build()
+            .unwrap();
+        let expected = expect![[r#"RUN ["make"]"#]];
+        expected.assert_eq(&run.to_string());
 
-        let from = FromBuilder::builder()
-            .image("cargo-chef")
-            .platform("linux/arm64")
+        let cmd = CmdExecBuilder::builder()
+            .executable("make")
+            .params(no_params.clone())
             .build()
             .unwrap();
-        let expected = expect!["FROM --platform=linux/arm64 cargo-chef"];
-        expected.assert_eq(&from.to_string());
+        let expected = expect![[r#"CMD ["make"]"#]];
+        expected.assert_eq(&cmd.to_string());
 
-        let from = FromBuilder::builder()
-            .image("cargo-chef")
-            .name("chef")
-            .tag("latest")
+        let entrypoint = EntrypointExecBuilder::builder()
+            .executable("make")
+            .params(no_params.clone())
             .build()
             .unwrap();
-        let expected = expect!["FROM cargo-chef:latest AS chef"];
-        expected.assert_eq(&from.to_string());
+        let expected = expect![[r#"ENTRYPOINT ["make"]"#]];
+        expected.assert_eq(&entrypoint.to_string());
 
-        let from = FromBuilder::builder()
-            .image("cargo-chef")
-            .name("chef")
-            .digest("sha256")
+        let shell = ShellBuilder::builder()
+            .executable("pwsh")
+            .params(no_params.clone())
             .build()
             .unwrap();
-        let expected = expect!["FROM cargo-chef@sha256 AS chef"];
-        expected.assert_eq(&from.to_string());
+        let expected = expect![[r#"SHELL ["pwsh"]"#]];
+        expected.assert_eq(&shell.to_string());
+
+        // params never set
+        let rendered = [
+            RunExecBuilder::builder()
+                .executable("make")
+                .build()
+                .unwrap()
+                .to_string(),
+            CmdExecBuilder::builder()
+                .executable("make")
+                .build()
+                .unwrap()
+                .to_string(),
+            EntrypointExecBuilder::builder()
+                .executable("make")
+                .build()
+                .unwrap()
+                .to_string(),
+            ShellBuilder::builder()
+                .executable("make")
+                .build()
+                .unwrap()
+                .to_string(),
+        ];
+        for instruction in rendered {
+            assert!(instruction.ends_with(r#" ["make"]"#), "{}", instruction);
+        }
+
+        let err = CmdExecBuilder::builder()
+            .params(no_params)
+            .build()
+            .unwrap_err();
+        let expected = expect!["CMD cannot be empty"];
+        expected.assert_eq(&err.to_string());
     }
 
     #[test]
-    fn run() {
+    fn workdir_require_absolute() {
+        for path in ["/app", "C:\\app", "c:/app", "$HOME", "${APP_DIR}/bin"] {
+            let workdir = WorkdirBuilder::builder()
+                .path(path)
+                .require_absolute(true)
+                .build();
+            assert!(workdir.is_ok(), "{} should be absolute", path);
+        }
+
+        let err = WorkdirBuilder::builder()
+            .path("app/bin")
+            .require_absolute(true)
+            .build()
+            .unwrap_err();
+        let expected = expect!["WORKDIR path must be absolute, got app/bin"];
+        expected.assert_eq(&err.to_string());
+
+        let workdir = WorkdirBuilder::builder().path("app/bin").build().unwrap();
+        let expected = expect!["WORKDIR app/bin"];
+        expected.assert_eq(&workdir.to_string());
+    }
+
+    #[test]
+    fn exec_escape() {
         let run = RunExecBuilder::builder()
-            .executable("mybin.exe")
+            .executable("echo")
+            .param(r#"say "hi""#)
             .build()
             .unwrap();
-        let expected = expect![r#"RUN ["mybin.exe"]"#];
+        let expected = expect![[r#"RUN ["echo", "say \"hi\""]"#]];
         expected.assert_eq(&run.to_string());
+
+        let cmd = CmdExecBuilder::builder().param(r"C:\app").build().unwrap();
+        let expected = expect![[r#"CMD ["C:\\app"]"#]];
+        expected.assert_eq(&cmd.to_string());
+
+        let entrypoint = EntrypointExecBuilder::builder()
+            .executable("sh")
+            .param("-c")
+            .param(r#"echo "$HOME""#)
+            .build()
+            .unwrap();
+        let json = &entrypoint.to_string()["ENTRYPOINT ".len()..];
+        let words: Vec<String> = serde_json::from_str(json).unwrap();
+        assert_eq!(words, vec!["sh", "-c", r#"echo "$HOME""#]);
     }
 
     #[test]
-    fn cmd() {
-        let cmd = CmdBuilder::builder()
-            .command("some command")
+    fn chmod_str() {
+        let copy = CopyBuilder::builder()
+            .chmod_str("0755")
+            .src("run.sh")
+            .dest("/bin/run.sh")
             .build()
             .unwrap();
-        let expected = expect!["CMD some command"];
-        expected.assert_eq(&cmd.to_string());
+        let expected = expect!["COPY --chmod=0755 run.sh /bin/run.sh"];
+        expected.assert_eq(&copy.to_string());
 
-        let cmd = CmdExecBuilder::builder()
-            .executable("some command")
+        let err = AddBuilder::builder()
+            .chmod(755)
+            .chmod_str("0755")
+            .src("run.sh")
+            .dest("/bin/run.sh")
             .build()
-            .unwrap();
-        let expected = expect![r#"CMD ["some command"]"#];
-        expected.assert_eq(&cmd.to_string());
+            .unwrap_err();
+        let expected = expect!["chmod and chmod_str cannot both be set"];
+        expected.assert_eq(&err.to_string());
+
+        let err = AddBuilder::builder()
+            .chmod_str("0o755")
+            .src("run.sh")
+            .dest("/bin/run.sh")
+            .build()
+            .unwrap_err();
+        let expected = expect!["chmod must be an octal mode like 0755, got 0o755"];
+        expected.assert_eq(&err.to_string());
     }
 
     #[test]
@@ -1139,11 +3448,105 @@ mod tests {
         }
     }
 
+    #[test]
+    fn label() {
+        let label = LabelBuilder::builder()
+            .key("maintainer")
+            .value("Jane Doe")
+            .label("version", "1.0")
+            .label("quote", r#"say "hi""#)
+            .build()
+            .unwrap();
+        let expected = expect![[r#"LABEL maintainer="Jane Doe" version=1.0 quote="say \"hi\"""#]];
+        expected.assert_eq(&label.to_string());
+    }
+
+    #[test]
+    fn label_env_quoting() {
+        let label = LabelBuilder::builder()
+            .key("description")
+            .value("My App")
+            .build()
+            .unwrap();
+        let expected = expect![[r#"LABEL description="My App""#]];
+        expected.assert_eq(&label.to_string());
+
+        let env = EnvBuilder::builder()
+            .env("JAVA_OPTS", "-Dfile.encoding=UTF-8")
+            .env("NAME", "O'Brien")
+            .env("SIMPLE", "/usr/local/bin")
+            .build()
+            .unwrap();
+        let expected = expect![[
+            r#"ENV JAVA_OPTS="-Dfile.encoding=UTF-8" NAME="O'Brien" SIMPLE=/usr/local/bin"#
+        ]];
+        expected.assert_eq(&env.to_string());
+    }
+
+    #[test]
+    fn label_err() {
+        let label = LabelBuilder::builder().key("foo").build();
+        match label {
+            Ok(_) => panic!("Key without value. Expect test to fail"),
+            Err(e) => assert_eq!(e.to_string(), "LABEL key and value must be set together"),
+        }
+
+        let label = LabelBuilder::builder().build();
+        match label {
+            Ok(_) => panic!("No labels. Expect test to fail"),
+            Err(e) => assert_eq!(e.to_string(), "LABEL requires at least one key/value pair"),
+        }
+    }
+
     #[test]
     fn expose() {
         let expose = ExposeBuilder::builder().port(80).build().unwrap();
         let expected = expect!["EXPOSE 80"];
         expected.assert_eq(&expose.to_string());
+
+        let expose = ExposeBuilder::builder()
+            .port_range(8000, 8010)
+            .build()
+            .unwrap();
+        let expected = expect!["EXPOSE 8000-8010"];
+        expected.assert_eq(&expose.to_string());
+
+        let err = ExposeBuilder::builder()
+            .port_range(8010, 8000)
+            .build()
+            .unwrap_err();
+        let expected = expect!["port range start 8010 is greater than its end 8000"];
+        expected.assert_eq(&err.to_string());
+
+        let expose = ExposeBuilder::builder()
+            .port(8080)
+            .protocol(Protocol::Udp)
+            .port_proto(80, None)
+            .port_proto(443, Some("tcp"))
+            .build()
+            .unwrap();
+        let expected = expect!["EXPOSE 8080/udp 80 443/tcp"];
+        expected.assert_eq(&expose.to_string());
+
+        let expose = ExposeBuilder::builder()
+            .port_proto(80, None)
+            .port_proto(443, Some("tcp"))
+            .build()
+            .unwrap();
+        let expected = expect!["EXPOSE 80 443/tcp"];
+        expected.assert_eq(&expose.to_string());
+
+        let err = ExposeBuilder::builder().build().unwrap_err();
+        let expected = expect!["EXPOSE requires at least one port"];
+        expected.assert_eq(&err.to_string());
+
+        let err = ExposeBuilder::builder()
+            .protocol(Protocol::Udp)
+            .port_proto(80, None)
+            .build()
+            .unwrap_err();
+        let expected = expect!["EXPOSE end_port and protocol require port"];
+        expected.assert_eq(&err.to_string());
     }
 
     #[test]
@@ -1155,6 +3558,30 @@ mod tests {
             .unwrap();
         let expected = expect!["ADD hom* /mydir/"];
         expected.assert_eq(&add.to_string());
+
+        let add = AddBuilder::builder()
+            .src("foo")
+            .src("bar")
+            .dest("/dir/")
+            .build()
+            .unwrap();
+        let expected = expect!["ADD foo bar /dir/"];
+        expected.assert_eq(&add.to_string());
+    }
+
+    #[test]
+    fn add_err() {
+        let add = AddBuilder::builder()
+            .srcs(vec!["foo", "bar"])
+            .dest("/dir")
+            .build();
+        match add {
+            Ok(_) => panic!("Multiple sources with a non-directory dest. Expect test to fail"),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "ADD dest must end with / when there are multiple sources",
+            ),
+        }
     }
 
     #[test]
@@ -1193,6 +3620,42 @@ mod tests {
             .unwrap();
         let expected = expect!["COPY --link foo/ bar/"];
         expected.assert_eq(&copy.to_string());
+
+        let copy = CopyBuilder::builder()
+            .srcs(vec!["a.txt", "b.txt"])
+            .dest("/app/")
+            .build()
+            .unwrap();
+        let expected = expect!["COPY a.txt b.txt /app/"];
+        expected.assert_eq(&copy.to_string());
+    }
+
+    #[test]
+    fn copy_from_index() {
+        let copy = CopyBuilder::builder()
+            .from_index(2)
+            .src("/target/release/app")
+            .dest("/usr/local/bin/")
+            .build()
+            .unwrap();
+        let expected = expect!["COPY --from=2 /target/release/app /usr/local/bin/"];
+        expected.assert_eq(&copy.to_string());
+    }
+
+    #[test]
+    fn copy_err() {
+        let copy = CopyBuilder::builder()
+            .src("a.txt")
+            .src("b.txt")
+            .dest("/app")
+            .build();
+        match copy {
+            Ok(_) => panic!("Multiple sources with a non-directory dest. Expect test to fail"),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "COPY dest must end with / when there are multiple sources",
+            ),
+        }
     }
 
     #[test]
@@ -1231,6 +3694,42 @@ mod tests {
         expected.assert_eq(&volume.to_string());
     }
 
+    #[test]
+    fn volume_exec() {
+        let volume = VolumeExecBuilder::builder()
+            .path("/my data")
+            .path(r#"C:\"quoted""#)
+            .build()
+            .unwrap();
+        let expected = expect![[r#"VOLUME ["/my data", "C:\\\"quoted\""]"#]];
+        expected.assert_eq(&volume.to_string());
+        let json = volume.to_string()["VOLUME ".len()..].to_string();
+        let paths: Vec<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(paths, vec!["/my data", r#"C:\"quoted""#]);
+
+        let err = VolumeExecBuilder::builder()
+            .path("/data")
+            .path(" ")
+            .build()
+            .unwrap_err();
+        let expected = expect!["VOLUME paths cannot be empty"];
+        expected.assert_eq(&err.to_string());
+    }
+
+    #[test]
+    fn maintainer() {
+        let maintainer = MaintainerBuilder::builder()
+            .name("Jane Doe")
+            .build()
+            .unwrap();
+        let expected = expect!["MAINTAINER Jane Doe"];
+        expected.assert_eq(&maintainer.to_string());
+
+        let err = MaintainerBuilder::builder().name(" ").build().unwrap_err();
+        let expected = expect!["MAINTAINER name cannot be empty"];
+        expected.assert_eq(&err.to_string());
+    }
+
     #[test]
     fn user() {
         let user = UserBuilder::builder().user("myuser").build().unwrap();
@@ -1271,6 +3770,116 @@ mod tests {
         expected.assert_eq(&arg.to_string());
     }
 
+    #[test]
+    fn env_arg_normalize_name() {
+        let env = EnvBuilder::builder()
+            .key("path")
+            .value("/bin")
+            .env("home", "/root")
+            .normalize_name(true)
+            .build()
+            .unwrap();
+        let expected = expect!["ENV PATH=/bin HOME=/root"];
+        expected.assert_eq(&env.to_string());
+
+        let env = EnvBuilder::builder()
+            .key("path")
+            .value("/bin")
+            .build()
+            .unwrap();
+        let expected = expect!["ENV path=/bin"];
+        expected.assert_eq(&env.to_string());
+
+        let arg = ArgBuilder::builder()
+            .name("version")
+            .value("1.0")
+            .normalize_name(true)
+            .build()
+            .unwrap();
+        let expected = expect!["ARG VERSION=1.0"];
+        expected.assert_eq(&arg.to_string());
+    }
+
+    #[test]
+    fn env_arg_empty_value() {
+        let env = EnvBuilder::builder().key("A").value("").build().unwrap();
+        let expected = expect![[r#"ENV A="""#]];
+        expected.assert_eq(&env.to_string());
+
+        let env = EnvBuilder::builder()
+            .env("A", "")
+            .env("B", "1")
+            .bare_empty(true)
+            .build()
+            .unwrap();
+        let expected = expect!["ENV A= B=1"];
+        expected.assert_eq(&env.to_string());
+
+        let arg = ArgBuilder::builder().name("A").value("").build().unwrap();
+        let expected = expect![[r#"ARG A="""#]];
+        expected.assert_eq(&arg.to_string());
+
+        let arg = ArgBuilder::builder()
+            .name("A")
+            .value("")
+            .bare_empty(true)
+            .build()
+            .unwrap();
+        let expected = expect!["ARG A="];
+        expected.assert_eq(&arg.to_string());
+    }
+
+    #[test]
+    fn arg_choices() {
+        let arg = ArgBuilder::builder()
+            .name("BUILD_MODE")
+            .value("debug")
+            .choices(vec!["debug", "release"])
+            .comment("build profile")
+            .build()
+            .unwrap();
+        let expected = expect![[r#"
+            # build profile
+            ARG BUILD_MODE=debug"#]];
+        expected.assert_eq(&arg.to_string());
+
+        let err = ArgBuilder::builder()
+            .name("BUILD_MODE")
+            .value("fast")
+            .choices(vec!["debug", "release"])
+            .build()
+            .unwrap_err();
+        let expected = expect!["ARG BUILD_MODE value fast is not one of debug|release"];
+        expected.assert_eq(&err.to_string());
+    }
+
+    #[test]
+    fn arg_predefined() {
+        let arg = ArgBuilder::predefined(PredefinedArg::BuildPlatform)
+            .build()
+            .unwrap();
+        let expected = expect!["ARG BUILDPLATFORM"];
+        expected.assert_eq(&arg.to_string());
+
+        let arg = ArgBuilder::predefined(PredefinedArg::HttpProxy)
+            .value("http://proxy:3128")
+            .build()
+            .unwrap();
+        let expected = expect!["ARG HTTP_PROXY=http://proxy:3128"];
+        expected.assert_eq(&arg.to_string());
+
+        assert_eq!(
+            PredefinedArg::from_name("TARGETARCH"),
+            Some(PredefinedArg::TargetArch)
+        );
+        assert_eq!(
+            PredefinedArg::from_name("no_proxy"),
+            Some(PredefinedArg::NoProxy)
+        );
+        assert_eq!(PredefinedArg::from_name("targetarch"), None);
+        assert_eq!(PredefinedArg::from_name("TARGET_ARCH"), None);
+    }
+
     #[test]
     fn onbuild() {
         let onbuild = OnbuildBuilder::builder()
@@ -1279,6 +3888,11 @@ mod tests {
             .unwrap();
         let expected = expect!["ONBUILD ADD . /app/src"];
         expected.assert_eq(&onbuild.to_string());
+
+        let run = RunBuilder::builder().command("make").build().unwrap();
+        let onbuild = OnbuildBuilder::builder().instruction(run).build().unwrap();
+        let expected = expect!["ONBUILD RUN make"];
+        expected.assert_eq(&onbuild.to_string());
     }
 
     #[test]
@@ -1308,14 +3922,79 @@ mod tests {
         }
     }
 
+    #[test]
+    fn onbuild_err_into_instruction() {
+        let err = OnbuildBuilder::builder()
+            .instruction(FROM::from("someimage"))
+            .build()
+            .unwrap_err();
+        let expected = expect!["ONBUILD instruction may not trigger FROM instruction"];
+        expected.assert_eq(&err.to_string());
+    }
+
+    #[test]
+    fn onbuild_comment() {
+        let run = RunBuilder::builder()
+            .command("make")
+            .comment("build")
+            .build()
+            .unwrap();
+        let onbuild = OnbuildBuilder::builder().instruction(run).build().unwrap();
+        let expected = expect!["ONBUILD RUN make"];
+        expected.assert_eq(&onbuild.to_string());
+
+        let err = OnbuildBuilder::builder()
+            .instruction(Instruction::COMMENT("build".to_string()))
+            .build()
+            .unwrap_err();
+        let expected = expect!["ONBUILD instruction may not trigger a comment"];
+        expected.assert_eq(&err.to_string());
+
+        let err = OnbuildBuilder::builder()
+            .instruction(MAINTAINER::from("me"))
+            .build()
+            .unwrap_err();
+        let expected = expect!["ONBUILD instruction may not trigger MAINTAINER instruction"];
+        expected.assert_eq(&err.to_string());
+    }
+
     #[test]
     fn stopsignal() {
         let stopsignal = StopsignalBuilder::builder()
-            .signal("SIGKILL")
+            .signal(Signal::Kill)
             .build()
             .unwrap();
         let expected = expect!["STOPSIGNAL SIGKILL"];
         expected.assert_eq(&stopsignal.to_string());
+
+        let stopsignal = StopsignalBuilder::builder()
+            .signal(Signal::Number(15))
+            .build()
+            .unwrap();
+        let expected = expect!["STOPSIGNAL 15"];
+        expected.assert_eq(&stopsignal.to_string());
+    }
+
+    #[test]
+    fn stopsignal_err() {
+        for signal in [Signal::Number(0), Signal::Number(65)] {
+            let stopsignal = StopsignalBuilder::builder().signal(signal).build();
+            assert!(stopsignal.is_err());
+        }
+        let stopsignal = StopsignalBuilder::builder().signal_name(" ").build();
+        assert!(stopsignal.is_err());
+    }
+
+    #[test]
+    fn shell() {
+        let shell = ShellBuilder::builder()
+            .executable(r"c:\sh")
+            .param("-c")
+            .param("echo \"hi\"\n")
+            .build()
+            .unwrap();
+        let expected = expect![[r#"SHELL ["c:\\sh", "-c", "echo \"hi\"\n"]"#]];
+        expected.assert_eq(&shell.to_string());
     }
 
     #[test]
@@ -1329,13 +4008,67 @@ mod tests {
 
         let healthcheck = HealthcheckBuilder::builder()
             .cmd(CMD::from("curl -f http://localhost/"))
-            .interval(15)
-            .timeout(200)
-            .start_period(5)
+            .interval(Duration::from_secs(15))
+            .timeout(Duration::from_secs(200))
+            .start_period(Duration::from_millis(5500))
             .retries(5)
             .build()
             .unwrap();
-        let expected = expect!["HEALTHCHECK --interal=15 --timeout=200 --start-period=5 --retries=5 CMD curl -f http://localhost/"];
+        let expected = expect!["HEALTHCHECK --interval=15s --timeout=3m20s --start-period=5s500ms --retries=5 CMD curl -f http://localhost/"];
+        expected.assert_eq(&healthcheck.to_string());
+    }
+
+    #[test]
+    fn healthcheck_flag_order() {
+        let healthcheck = HealthcheckBuilder::builder()
+            .retries(3)
+            .start_interval(Duration::from_secs(2))
+            .start_period(Duration::from_secs(60))
+            .timeout(Duration::from_secs(5))
+            .interval(Duration::from_secs(30))
+            .cmd(CMD::from("curl -f http://localhost/"))
+            .build()
+            .unwrap();
+        let expected = expect!["HEALTHCHECK --interval=30s --timeout=5s --start-period=1m --start-interval=2s --retries=3 CMD curl -f http://localhost/"];
+        expected.assert_eq(&healthcheck.to_string());
+    }
+
+    #[test]
+    fn healthcheck_none() {
+        let healthcheck = HealthcheckBuilder::builder().none(true).build().unwrap();
+        let expected = expect!["HEALTHCHECK NONE"];
         expected.assert_eq(&healthcheck.to_string());
+
+        let healthcheck = HealthcheckBuilder::builder()
+            .none(true)
+            .interval(Duration::from_secs(30))
+            .build();
+        match healthcheck {
+            Ok(_) => panic!("NONE combined with options. Expect test to fail"),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "HEALTHCHECK NONE cannot be combined with a command or options",
+            ),
+        }
+
+        let healthcheck = HealthcheckBuilder::builder().build();
+        match healthcheck {
+            Ok(_) => panic!("Neither cmd nor none is set. Expect test to fail"),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "cmd is required for HealthcheckBuilder unless none is set",
+            ),
+        }
+    }
+
+    #[test]
+    fn duration() {
+        assert_eq!(format_duration(Duration::ZERO), "0s");
+        assert_eq!(format_duration(Duration::from_secs(30)), "30s");
+        assert_eq!(format_duration(Duration::from_secs(90)), "1m30s");
+        assert_eq!(format_duration(Duration::from_secs(3600)), "1h");
+        assert_eq!(format_duration(Duration::from_secs(3723)), "1h2m3s");
+        assert_eq!(format_duration(Duration::from_millis(500)), "500ms");
+        assert_eq!(format_duration(Duration::from_micros(15)), "15us");
     }
 }
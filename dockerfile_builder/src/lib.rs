@@ -38,13 +38,13 @@
 //! ```rust
 //!use dockerfile_builder::Dockerfile;
 //!use dockerfile_builder::instruction::EXPOSE;
-//!use dockerfile_builder::instruction_builder::ExposeBuilder;
+//!use dockerfile_builder::instruction_builder::{ExposeBuilder, Protocol};
 //!
 //!let expose = EXPOSE::from("80/tcp");
 //!
 //!let expose_from_builder = ExposeBuilder::builder()
 //!    .port(80)
-//!    .protocol("tcp")
+//!    .protocol(Protocol::Tcp)
 //!    .build()
 //!    .unwrap();
 //!
@@ -59,21 +59,144 @@
 //!);
 //!
 //! ```
+//!
+//! ## Parse Dockerfile
+//!
+//! An existing Dockerfile can be parsed with [`str::parse`], modified and rendered again.
+//!
+//! ```rust
+//!use dockerfile_builder::Dockerfile;
+//!use dockerfile_builder::instruction::EXPOSE;
+//!
+//!let dockerfile: Dockerfile = "FROM alpine\nRUN echo $HOME".parse().unwrap();
+//!let dockerfile = dockerfile.push(EXPOSE::from("80"));
+//!
+//!assert_eq!(
+//!    dockerfile.to_string(),
+//!    "FROM alpine\nRUN echo $HOME\nEXPOSE 80"
+//!);
+//! ```
+//!
+//! # Features
+//!
+//! * `tar`: Adds helpers to write the rendered Dockerfile into a tar archive, see [`archive`].
+//! * `serde`: Derives `Serialize` and `Deserialize` for [`Dockerfile`] and the
+//!   [instructions](instruction). [`Instruction`](instruction::Instruction) is tagged with its
+//!   variant name, e.g. `{"FROM": {"value": "alpine"}}`.
+//! * `testing`: Adds [`Dockerfile::sample_all`], a Dockerfile using every instruction, e.g. for
+//!   snapshot tests.
 
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{self, Display};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::{Add, Index};
+use std::path::Path;
 
-use instruction::Instruction;
+use instruction::{Instruction, ARG, LABEL};
+use instruction_builder::{
+    ArgBuilderInner, BuildResult, BuilderError, CmdExecBuilder, EntrypointExecBuilder, EnvBuilder,
+};
+use render::RenderOptions;
 
+pub mod analysis;
+#[cfg(feature = "tar")]
+pub mod archive;
 pub mod instruction;
 pub mod instruction_builder;
+pub mod lint;
+pub mod parser;
+pub mod render;
+pub mod stage;
+pub mod validation;
 
 /// Dockerfile builder
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Dockerfile {
     instructions: Vec<Instruction>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    syntax: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    escape: Option<char>,
+    /// Choices of the `ARG`s added with `push_arg`, by name
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    arg_choices: BTreeMap<String, Vec<String>>,
 }
 
 impl Dockerfile {
+    /// Creates an empty Dockerfile, same as [`Dockerfile::default`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty Dockerfile with room for at least `capacity` instructions
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::RUN;
+    /// let dockerfile = (0..3).fold(Dockerfile::with_capacity(3), |d, i| {
+    ///     d.push(RUN::from(format!("echo {}", i)))
+    /// });
+    /// assert_eq!(dockerfile.len(), 3);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            instructions: Vec::with_capacity(capacity),
+            ..Self::default()
+        }
+    }
+
+    /// Returns a Dockerfile with one instruction of every kind, with representative values
+    ///
+    /// Useful as a snapshot of how every [`Instruction`] is rendered. Only available with the
+    /// `testing` feature.
+    #[cfg(any(test, feature = "testing"))]
+    pub fn sample_all() -> Self {
+        use instruction::*;
+        Dockerfile::default()
+            .syntax("docker/dockerfile:1")
+            .comment("sample of every instruction")
+            .push(ARG::from("RUST_VERSION=1.70"))
+            .push(FROM::from("rust:${RUST_VERSION} AS builder"))
+            .push(MAINTAINER::from("Jane Doe <jane@example.com>"))
+            .push(LABEL::from(
+                r#"org.opencontainers.image.title="sample app""#,
+            ))
+            .push(ENV::from("RUST_LOG=info"))
+            .push(SHELL::from(r#"["/bin/bash", "-o", "pipefail", "-c"]"#))
+            .push(WORKDIR::from("/app"))
+            .push(ADD::from(
+                "--checksum=sha256:abc https://example.com/data.tar.gz /data/",
+            ))
+            .push(COPY::from("--chown=1000:1000 . ."))
+            .push(RUN::from("cargo build --release"))
+            .push(FROM::from("debian:bookworm-slim"))
+            .push(COPY::from(
+                "--from=builder /app/target/release/app /usr/local/bin/app",
+            ))
+            .push(USER::from("1000:1000"))
+            .push(VOLUME::from(r#"["/data"]"#))
+            .push(EXPOSE::from("8080/tcp"))
+            .push(ONBUILD::from("RUN echo triggered"))
+            .push(STOPSIGNAL::from("SIGTERM"))
+            .push(HEALTHCHECK::from(
+                "--interval=30s CMD curl -f http://localhost:8080/",
+            ))
+            .push(ENTRYPOINT::from(r#"["app"]"#))
+            .push(CMD::from(r#"["--port", "8080"]"#))
+            .push_any("# raw line")
+    }
+
     /// Adds an [`Instruction`] to the end of the Dockerfile
     ///
     /// [Instruction]: instruction::Instruction
@@ -82,6 +205,33 @@ impl Dockerfile {
         self
     }
 
+    /// Builds an `ARG` and adds it to the end of the Dockerfile
+    ///
+    /// Unlike `push`, the choices of the builder are kept and returned by
+    /// [`Dockerfile::build_args`]. They aren't rendered, so they're lost when the Dockerfile is
+    /// parsed back.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction_builder::ArgBuilder;
+    /// let dockerfile = Dockerfile::default()
+    ///     .push_arg(ArgBuilder::builder().name("MODE").choices(vec!["debug", "release"]))
+    ///     .unwrap();
+    /// assert_eq!(dockerfile.to_string(), "ARG MODE");
+    /// assert_eq!(
+    ///     dockerfile.build_args()[0].choices,
+    ///     Some(vec!["debug".to_string(), "release".to_string()]),
+    /// );
+    /// ```
+    pub fn push_arg(mut self, arg: &mut ArgBuilderInner) -> BuildResult<Self> {
+        let built = arg.build()?;
+        if let Some(choices) = arg.allowed_choices() {
+            let name = built.value.split('=').next().unwrap_or_default();
+            self.arg_choices.insert(name.to_string(), choices.clone());
+        }
+        Ok(self.push(built))
+    }
+
     /// Adds any raw string to the end of the Dockerfile
     pub fn push_any<T: Into<String>>(mut self, instruction: T) -> Self {
         self.instructions.push(Instruction::ANY(instruction.into()));
@@ -106,19 +256,457 @@ impl Dockerfile {
         self
     }
 
-    /// Adds `syntax` data to the end of the Dockerfile
-    pub fn syntax<T: Into<String>>(self, syntax: T) -> Self {
-        self.push_any(format!("# syntax={}", syntax.into()))
+    /// Sets the frontend used to build the Dockerfile with the `# syntax=` parser directive
+    ///
+    /// The directive is always rendered on the first line, before the `# escape=` directive.
+    /// Calling `syntax` again replaces the reference.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::FROM;
+    /// let dockerfile = Dockerfile::default()
+    ///     .push(FROM::from("alpine"))
+    ///     .syntax("docker/dockerfile:1.4")
+    ///     .syntax("docker/dockerfile:1");
+    /// assert_eq!(dockerfile.to_string(), "# syntax=docker/dockerfile:1\nFROM alpine");
+    /// ```
+    pub fn syntax<T: Into<String>>(mut self, reference: T) -> Self {
+        self.syntax = Some(reference.into());
+        self
     }
 
     /// Adds `escape` data to the end of the Dockerfile
+    ///
+    /// The directive only takes effect on the first line, use [`Dockerfile::escape_char`] instead.
     pub fn escape<T: Into<String>>(self, escape: T) -> Self {
         self.push_any(format!("# escape={}", escape.into()))
     }
 
+    /// Sets the escape character with the `# escape=` parser directive
+    ///
+    /// The directive is rendered on the first line. Line continuations of instructions, i.e.
+    /// trailing `\`, are rendered with the escape character.
+    /// Only `\` and `` ` `` are valid escape characters.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::{FROM, RUN};
+    /// let dockerfile = Dockerfile::default()
+    ///     .push(FROM::from("mcr.microsoft.com/windows/servercore"))
+    ///     .push(RUN::from("dir c:\\ \\\n    && echo done"))
+    ///     .escape_char('`')
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     dockerfile.to_string(),
+    ///     "# escape=`\nFROM mcr.microsoft.com/windows/servercore\nRUN dir c:\\ `\n    && echo done",
+    /// );
+    /// ```
+    pub fn escape_char(mut self, escape: char) -> BuildResult<Self> {
+        if escape != '\\' && escape != '`' {
+            return Err(BuilderError::Invalid(format!(
+                "escape character must be \\ or `, got {}",
+                escape
+            )));
+        }
+        self.escape = Some(escape);
+        Ok(self)
+    }
+
     /// Adds a comment to the end of the Dockerfile
+    ///
+    /// The comment is added as [`Instruction::COMMENT`], every line is prefixed with `# `. Use the
+    /// `comment()` setter of the builders instead to attach a comment to an instruction.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// let dockerfile = Dockerfile::default().comment("build stage\ncompiles the app");
+    /// assert_eq!(dockerfile.to_string(), "# build stage\n# compiles the app");
+    /// ```
     pub fn comment<T: Into<String>>(self, comment: T) -> Self {
-        self.push_any(format!("# {}", comment.into()))
+        self.push(Instruction::COMMENT(comment.into()))
+    }
+
+    /// Adds a "do not edit" banner to the start of the Dockerfile
+    ///
+    /// Parser directives must come first, so the banner is rendered after all directives, e.g.
+    /// `syntax`, `escape` or a parsed `check`, and before the first instruction.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::FROM;
+    /// let dockerfile = Dockerfile::default()
+    ///     .syntax("docker/dockerfile:1")
+    ///     .push(FROM::from("alpine"))
+    ///     .with_generated_header("my-tool", Some("2023-06-01T00:00:00Z"));
+    /// let lines = dockerfile.to_string();
+    /// let lines = lines.lines().collect::<Vec<_>>();
+    /// assert_eq!(
+    ///     lines,
+    ///     vec![
+    ///         "# syntax=docker/dockerfile:1",
+    ///         "# This file is generated by my-tool, do not edit it manually.",
+    ///         "# Generated at 2023-06-01T00:00:00Z",
+    ///         "FROM alpine",
+    ///     ],
+    /// );
+    /// ```
+    pub fn with_generated_header<T: Into<String>>(
+        mut self,
+        tool_name: T,
+        timestamp: Option<&str>,
+    ) -> Self {
+        let mut banner = vec![Instruction::COMMENT(format!(
+            "This file is generated by {}, do not edit it manually.",
+            tool_name.into()
+        ))];
+        banner.extend(timestamp.map(|t| Instruction::COMMENT(format!("Generated at {}", t))));
+        let directives = self
+            .instructions
+            .iter()
+            .take_while(|i| matches!(i, Instruction::ANY(raw) if parser::directive(raw).is_some()))
+            .count();
+        self.instructions.splice(directives..directives, banner);
+        self
+    }
+
+    /// Removes all comments from the Dockerfile
+    ///
+    /// [`Instruction::COMMENT`]s, `ANY` lines starting with `#` and the comments of instructions
+    /// are removed. The `syntax`, `escape` and `check` parser directives are kept, as they change
+    /// how the Dockerfile is built.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::FROM;
+    /// let dockerfile = Dockerfile::default()
+    ///     .syntax("docker/dockerfile:1")
+    ///     .comment("base image")
+    ///     .push(FROM::from("alpine"))
+    ///     .strip_comments();
+    /// assert_eq!(dockerfile.to_string(), "# syntax=docker/dockerfile:1\nFROM alpine");
+    /// ```
+    pub fn strip_comments(mut self) -> Self {
+        self.instructions.retain(|instruction| match instruction {
+            Instruction::COMMENT(_) => false,
+            Instruction::ANY(raw) if raw.trim_start().starts_with('#') => {
+                matches!(
+                    parser::directive(raw),
+                    Some((key, _)) if matches!(key.as_str(), "syntax" | "escape" | "check")
+                )
+            }
+            _ => true,
+        });
+        for instruction in &mut self.instructions {
+            if let Some(comment) = instruction.comment_mut() {
+                *comment = None;
+            }
+        }
+        self
+    }
+
+    /// Adds an exec form `ENTRYPOINT` followed by an exec form `CMD` supplying its default
+    /// arguments
+    ///
+    /// The default arguments are replaced by the arguments passed to `docker run`. Both
+    /// instructions use the exec form, as a shell form `ENTRYPOINT` ignores `CMD`. Returns an
+    /// error if `exec` or `default_args` is empty.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// let dockerfile = Dockerfile::default()
+    ///     .entrypoint_with_default_cmd(vec!["app", "serve"], vec!["--port", "8080"])
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     dockerfile.to_string(),
+    ///     "ENTRYPOINT [\"app\", \"serve\"]\nCMD [\"--port\", \"8080\"]",
+    /// );
+    /// ```
+    pub fn entrypoint_with_default_cmd<T: Into<String>, U: Into<String>>(
+        self,
+        exec: Vec<T>,
+        default_args: Vec<U>,
+    ) -> BuildResult<Self> {
+        let mut exec = exec.into_iter().map(Into::into);
+        let executable = exec
+            .next()
+            .ok_or_else(|| BuilderError::Invalid("ENTRYPOINT cannot be empty".to_string()))?;
+        let entrypoint = EntrypointExecBuilder::builder()
+            .executable(executable)
+            .params(exec.collect::<Vec<_>>())
+            .build()?;
+        let cmd = CmdExecBuilder::builder().params(default_args).build()?;
+        Ok(self.push(entrypoint).push(cmd))
+    }
+
+    /// Adds the environment variables of `envs` to the end of the Dockerfile, in key order
+    ///
+    /// If `combined` is set, all variables are set by a single `ENV`, otherwise every variable
+    /// gets its own `ENV`. Nothing is added if `envs` is empty.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use std::collections::BTreeMap;
+    /// let envs = BTreeMap::from([("LANG", "C.UTF-8"), ("TZ", "UTC")]);
+    ///
+    /// let dockerfile = Dockerfile::default().env_vars(envs.clone(), true).unwrap();
+    /// assert_eq!(dockerfile.to_string(), "ENV LANG=C.UTF-8 TZ=UTC");
+    ///
+    /// let dockerfile = Dockerfile::default().env_vars(envs, false).unwrap();
+    /// assert_eq!(dockerfile.to_string(), "ENV LANG=C.UTF-8\nENV TZ=UTC");
+    /// ```
+    pub fn env_vars<K: Into<String>, V: Into<String>>(
+        self,
+        envs: BTreeMap<K, V>,
+        combined: bool,
+    ) -> BuildResult<Self> {
+        if envs.is_empty() {
+            return Ok(self);
+        }
+        if combined {
+            return Ok(self.push(EnvBuilder::from_map(envs).build()?));
+        }
+        envs.into_iter().try_fold(self, |dockerfile, (key, value)| {
+            let env = EnvBuilder::builder().key(key).value(value).build()?;
+            Ok(dockerfile.push(env))
+        })
+    }
+
+    /// Adds `ARG BUILDKIT_INLINE_CACHE=1` to the end of the Dockerfile
+    ///
+    /// BuildKit then embeds the build cache metadata in the image, so it can be used with
+    /// `--cache-from` by later builds. Nothing is added if `BUILDKIT_INLINE_CACHE` is already
+    /// declared.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::FROM;
+    /// let dockerfile = Dockerfile::default()
+    ///     .push(FROM::from("alpine"))
+    ///     .enable_inline_cache();
+    /// assert_eq!(dockerfile.to_string(), "FROM alpine\nARG BUILDKIT_INLINE_CACHE=1");
+    /// ```
+    pub fn enable_inline_cache(self) -> Self {
+        if self
+            .build_args()
+            .iter()
+            .any(|arg| arg.name == "BUILDKIT_INLINE_CACHE")
+        {
+            return self;
+        }
+        self.push(ARG::from("BUILDKIT_INLINE_CACHE=1"))
+    }
+
+    /// Adds `ARG SOURCE_DATE_EPOCH` to the end of the Dockerfile, with `epoch` as default value
+    ///
+    /// BuildKit uses the build argument as the timestamp of the image and its layers, for
+    /// reproducible builds. It's usually set with `--build-arg SOURCE_DATE_EPOCH=$(git log -1
+    /// --pretty=%ct)`.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// let dockerfile = Dockerfile::default().with_source_date_epoch(Some(1700000000));
+    /// assert_eq!(dockerfile.to_string(), "ARG SOURCE_DATE_EPOCH=1700000000");
+    ///
+    /// let dockerfile = Dockerfile::default().with_source_date_epoch(None);
+    /// assert_eq!(dockerfile.to_string(), "ARG SOURCE_DATE_EPOCH");
+    /// ```
+    pub fn with_source_date_epoch(self, epoch: Option<u64>) -> Self {
+        let arg = match epoch {
+            Some(epoch) => format!("SOURCE_DATE_EPOCH={}", epoch),
+            None => "SOURCE_DATE_EPOCH".to_string(),
+        };
+        self.push(ARG::from(arg))
+    }
+
+    /// Adds the image author to the end of the Dockerfile
+    ///
+    /// The `MAINTAINER` instruction is deprecated. As recommended by the [Dockerfile reference],
+    /// this emits `LABEL org.opencontainers.image.authors=<name>` instead. The name is quoted
+    /// like the values of [`LabelBuilder`](instruction_builder::LabelBuilder).
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// let dockerfile = Dockerfile::default().maintainer("Jane Doe");
+    /// assert_eq!(
+    ///     dockerfile.to_string(),
+    ///     r#"LABEL org.opencontainers.image.authors="Jane Doe""#,
+    /// );
+    /// ```
+    ///
+    /// [Dockerfile reference]: https://docs.docker.com/engine/reference/builder/#maintainer-deprecated
+    pub fn maintainer<T: Into<String>>(self, name: T) -> Self {
+        self.push(LABEL::from(format!(
+            "org.opencontainers.image.authors={}",
+            instruction_builder::quote_value(&name.into())
+        )))
+    }
+
+    /// Pins the images of `FROM` instructions to digests returned by `resolver`
+    ///
+    /// `resolver` is called with the image reference, e.g. `rust:1.70`, and returns its digest,
+    /// e.g. `sha256:...`. The image is then rewritten to `<image>@<digest>`, dropping the tag.
+    /// Images already pinned to a digest, `scratch`, references to previous build stages, images
+    /// using variables, e.g. `${BASE}`, and images the resolver returns `None` for are left
+    /// unchanged. The resolver is only called for images that can be pinned. Only the image is
+    /// replaced, the rest of the `FROM` is kept as is.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::FROM;
+    /// let dockerfile = Dockerfile::default()
+    ///     .push(FROM::from("rust:1.70 AS builder"))
+    ///     .pin_from_digests(|image| match image {
+    ///         "rust:1.70" => Some("sha256:abc".to_string()),
+    ///         _ => None,
+    ///     });
+    /// assert_eq!(dockerfile.to_string(), "FROM rust@sha256:abc AS builder");
+    /// ```
+    pub fn pin_from_digests<F: Fn(&str) -> Option<String>>(mut self, resolver: F) -> Self {
+        let mut stages: Vec<String> = vec![];
+        for instruction in &mut self.instructions {
+            let Instruction::FROM(from) = instruction else {
+                continue;
+            };
+            let (image, name) = lint::parse_from(&from.value);
+            let (image, name) = (image.to_string(), name.map(|n| n.to_lowercase()));
+            let pinnable = !image.contains(['@', '$'])
+                && image != "scratch"
+                && !stages.contains(&image.to_lowercase());
+            if let Some(digest) = pinnable.then(|| resolver(&image)).flatten() {
+                let repository = match image.rsplit_once(':') {
+                    Some((repository, tag)) if !tag.contains('/') => repository,
+                    _ => &image,
+                };
+                let pinned = format!("{}@{}", repository, digest);
+                if let Some(range) = word_range(&from.value, &image) {
+                    from.value.replace_range(range, &pinned);
+                }
+            }
+            stages.extend(name);
+        }
+        self
+    }
+
+    /// Replaces `{{KEY}}` placeholders in the value of every instruction with the value of `KEY`
+    /// in `vars`
+    ///
+    /// The substitution happens when generating the Dockerfile, unlike `ARG` and `ENV` which are
+    /// expanded by Docker at build time. Placeholders without a value in `vars` are kept as is.
+    /// Each value is inserted once, placeholders it contains aren't replaced.
+    ///
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::FROM;
+    /// let dockerfile = Dockerfile::default()
+    ///     .push(FROM::from("rust:{{VERSION}}"))
+    ///     .substitute(&HashMap::from([("VERSION", "1.70")]));
+    /// assert_eq!(dockerfile.to_string(), "FROM rust:1.70");
+    /// ```
+    pub fn substitute(mut self, vars: &HashMap<&str, &str>) -> Self {
+        for instruction in &mut self.instructions {
+            let value = instruction.value_mut();
+            let mut substituted = String::with_capacity(value.len());
+            let mut rest = value.as_str();
+            while let Some(start) = rest.find("{{") {
+                let (before, placeholder) = rest.split_at(start);
+                substituted.push_str(before);
+                let var = placeholder[2..]
+                    .find("}}")
+                    .and_then(|end| Some((vars.get(&placeholder[2..2 + end])?, end)));
+                match var {
+                    Some((var, end)) => {
+                        substituted.push_str(var);
+                        rest = &placeholder[end + 4..];
+                    }
+                    None => {
+                        substituted.push_str("{{");
+                        rest = &placeholder[2..];
+                    }
+                }
+            }
+            substituted.push_str(rest);
+            *value = substituted;
+        }
+        self
+    }
+
+    /// Calls a closure with the current Dockerfile and returns it unchanged
+    ///
+    /// Useful for debugging or logging in the middle of a builder chain.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::{FROM, RUN};
+    /// let dockerfile = Dockerfile::default()
+    ///     .push(FROM::from("alpine"))
+    ///     .inspect(|d| println!("{}", d))
+    ///     .push(RUN::from("echo $HOME"));
+    /// ```
+    pub fn inspect<F: Fn(&Dockerfile)>(self, f: F) -> Self {
+        f(&self);
+        self
+    }
+
+    /// Returns `true` if both Dockerfiles render the same instructions, ignoring trailing
+    /// whitespace on every line and the `comment` of the instructions
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::{CMD, FROM};
+    /// let a = Dockerfile::default().push(FROM::from("alpine")).push(CMD::from("sh "));
+    /// let b = Dockerfile::default().push(FROM::from("alpine")).push(CMD::from("sh"));
+    /// assert!(a.normalized_eq(&b));
+    /// ```
+    pub fn normalized_eq(&self, other: &Dockerfile) -> bool {
+        fn normalize(line: String) -> Vec<String> {
+            line.lines().map(|l| l.trim_end().to_string()).collect()
+        }
+        fn uncommented(dockerfile: &Dockerfile) -> Dockerfile {
+            let mut dockerfile = dockerfile.clone();
+            for instruction in dockerfile.instructions.iter_mut() {
+                if let Some(comment) = instruction.comment_mut() {
+                    *comment = None;
+                }
+            }
+            dockerfile
+        }
+        let options = RenderOptions::default();
+        uncommented(self)
+            .lines(&options)
+            .map(normalize)
+            .eq(uncommented(other).lines(&options).map(normalize))
+    }
+
+    /// Writes the Dockerfile to `w`, one instruction at a time
+    ///
+    /// Unlike [`Dockerfile::to_string`], every instruction, including the last one, is followed
+    /// by `\n`. Use [`RenderOptions::trailing_newline`] to render the same output as a string.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::{FROM, RUN};
+    /// let dockerfile = Dockerfile::default()
+    ///     .push(FROM::from("alpine"))
+    ///     .push(RUN::from("echo $HOME"));
+    /// let mut out = Vec::new();
+    /// dockerfile.to_writer(&mut out).unwrap();
+    /// assert_eq!(out, b"FROM alpine\nRUN echo $HOME\n");
+    /// ```
+    pub fn to_writer<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for line in self.lines(&RenderOptions::default()) {
+            writeln!(w, "{}", line)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the Dockerfile to the file at `path`, see [`Dockerfile::to_writer`]
+    ///
+    /// The file is created if it doesn't exist and truncated if it does.
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.to_writer(&mut file)?;
+        file.flush()
     }
 
     /// Retrieves [`Instruction`] vec from Dockerfile
@@ -127,16 +715,233 @@ impl Dockerfile {
     pub fn into_inner(self) -> Vec<Instruction> {
         self.instructions
     }
+
+    /// Returns the number of instructions
+    ///
+    /// The directives set with [`Dockerfile::syntax`] and [`Dockerfile::escape_char`] aren't
+    /// counted. Directives added as raw lines, e.g. with [`Dockerfile::escape`] or parsed
+    /// `# check=` lines, are counted.
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    /// Returns `true` if the Dockerfile has no instructions
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    /// Returns the instruction at `index`, or `None` if it's out of bounds
+    ///
+    /// Use indexing, e.g. `dockerfile[0]`, to panic on out of bounds instead.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::{Instruction, FROM};
+    /// let dockerfile = Dockerfile::default().push(FROM::from("alpine"));
+    /// assert_eq!(dockerfile[0], Instruction::from(FROM::from("alpine")));
+    /// assert_eq!(dockerfile.get(0), Some(&dockerfile[0]));
+    /// assert_eq!(dockerfile.get(1), None);
+    /// ```
+    pub fn get(&self, index: usize) -> Option<&Instruction> {
+        self.instructions.get(index)
+    }
+
+    /// Returns an iterator over the instructions
+    ///
+    /// `&Dockerfile` and `Dockerfile` also implement [`IntoIterator`].
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::{Instruction, FROM, RUN};
+    /// let dockerfile = Dockerfile::default()
+    ///     .push(FROM::from("alpine"))
+    ///     .push(RUN::from("apk add curl"));
+    /// let runs = dockerfile
+    ///     .iter()
+    ///     .filter(|i| matches!(i, Instruction::RUN(_)))
+    ///     .count();
+    /// assert_eq!(runs, 1);
+    /// ```
+    pub fn iter(&self) -> std::slice::Iter<'_, Instruction> {
+        self.instructions.iter()
+    }
+
+    /// Removes and returns the instruction at `index`, or `None` if it's out of bounds
+    pub fn remove(&mut self, index: usize) -> Option<Instruction> {
+        (index < self.instructions.len()).then(|| self.instructions.remove(index))
+    }
+
+    /// Inserts an instruction at `index`, shifting all instructions after it
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`, like [`Vec::insert`].
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::{FROM, RUN, USER};
+    /// let mut dockerfile = Dockerfile::default()
+    ///     .push(FROM::from("alpine"))
+    ///     .push(RUN::from("apk add curl"));
+    /// dockerfile.insert(1, USER::from("root"));
+    /// dockerfile.remove(2);
+    /// assert_eq!(dockerfile.to_string(), "FROM alpine\nUSER root");
+    /// ```
+    pub fn insert<T: Into<Instruction>>(&mut self, index: usize, instruction: T) {
+        self.instructions.insert(index, instruction.into());
+    }
+
+    /// Inserts an [`Instruction`] right after the `FROM` of the build stage named `stage_name`
+    ///
+    /// Stage names are compared case-insensitively, like Docker does. The Dockerfile is
+    /// returned unchanged if no stage has this name.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::{ARG, FROM, RUN};
+    /// let dockerfile = Dockerfile::default()
+    ///     .push(FROM::from("rust AS builder"))
+    ///     .push(RUN::from("cargo build"))
+    ///     .insert_after_from("builder", ARG::from("PROFILE=release"));
+    /// assert_eq!(
+    ///     dockerfile.to_string(),
+    ///     "FROM rust AS builder\nARG PROFILE=release\nRUN cargo build",
+    /// );
+    /// ```
+    pub fn insert_after_from<T: Into<Instruction>>(
+        mut self,
+        stage_name: &str,
+        instruction: T,
+    ) -> Self {
+        let from = self.instructions.iter().position(|i| match i {
+            Instruction::FROM(from) => lint::parse_from(&from.value)
+                .1
+                .is_some_and(|name| name.eq_ignore_ascii_case(stage_name)),
+            _ => false,
+        });
+        if let Some(index) = from {
+            self.instructions.insert(index + 1, instruction.into());
+        }
+        self
+    }
+}
+
+impl Dockerfile {
+    /// Renders the parser directives and then each instruction
+    fn lines<'a>(&'a self, options: &'a RenderOptions) -> impl Iterator<Item = String> + 'a {
+        let syntax = self.syntax.as_ref().map(|s| format!("# syntax={}", s));
+        let escape = self.escape.map(|c| format!("# escape={}", c));
+        let instructions = self.instructions.iter().map(move |i| {
+            let rendered = render::render_instruction(i, options);
+            match (i, self.escape) {
+                (Instruction::ANY(_) | Instruction::COMMENT(_), _) | (_, None | Some('\\')) => {
+                    rendered
+                }
+                (_, Some(escape)) => with_escape_char(&rendered, escape),
+            }
+        });
+        syntax.into_iter().chain(escape).chain(instructions)
+    }
+}
+
+/// Returns the byte range of the first whitespace separated word of `value` equal to `word`
+fn word_range(value: &str, word: &str) -> Option<std::ops::Range<usize>> {
+    let mut offset = 0;
+    for token in value.split_whitespace() {
+        let start = offset + value[offset..].find(token)?;
+        offset = start + token.len();
+        if token == word {
+            return Some(start..offset);
+        }
+    }
+    None
+}
+
+/// Replaces the `\` of the line continuations of a rendered instruction with `escape`
+///
+/// Comment lines are kept as is, and the continuations stop at the first line without one, e.g.
+/// before a heredoc body. A `\` ending the last line isn't a continuation, e.g. `WORKDIR C:\`.
+fn with_escape_char(instruction: &str, escape: char) -> String {
+    let mut continued = true;
+    let mut lines = instruction.split('\n').peekable();
+    let mut escaped = vec![];
+    while let Some(line) = lines.next() {
+        if line.starts_with('#') || !continued {
+            escaped.push(line.to_string());
+            continue;
+        }
+        match line.strip_suffix('\\') {
+            Some(head) if lines.peek().is_some() => escaped.push(format!("{}{}", head, escape)),
+            _ => {
+                continued = false;
+                escaped.push(line.to_string());
+            }
+        }
+    }
+    escaped.join("\n")
+}
+
+impl Extend<Instruction> for Dockerfile {
+    fn extend<T: IntoIterator<Item = Instruction>>(&mut self, iter: T) {
+        self.instructions.extend(iter);
+    }
+}
+
+/// Appends the instructions of another Dockerfile
+///
+/// Parser directives of the left-hand side take precedence, those of the right-hand side are
+/// only kept if the left-hand side doesn't set them.
+///
+/// ```
+/// # use dockerfile_builder::Dockerfile;
+/// # use dockerfile_builder::instruction::{FROM, RUN};
+/// let base = Dockerfile::default().push(FROM::from("alpine"));
+/// let app = Dockerfile::default().push(RUN::from("apk add curl"));
+/// assert_eq!((base + app).to_string(), "FROM alpine\nRUN apk add curl");
+/// ```
+impl Add for Dockerfile {
+    type Output = Dockerfile;
+
+    fn add(mut self, other: Dockerfile) -> Self::Output {
+        self.syntax = self.syntax.or(other.syntax);
+        self.escape = self.escape.or(other.escape);
+        for (name, choices) in other.arg_choices {
+            self.arg_choices.entry(name).or_insert(choices);
+        }
+        self.extend(other.instructions);
+        self
+    }
+}
+
+impl Index<usize> for Dockerfile {
+    type Output = Instruction;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.instructions[index]
+    }
+}
+
+impl IntoIterator for Dockerfile {
+    type Item = Instruction;
+    type IntoIter = std::vec::IntoIter<Instruction>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.instructions.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Dockerfile {
+    type Item = &'a Instruction;
+    type IntoIter = std::slice::Iter<'a, Instruction>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.instructions.iter()
+    }
 }
 
 impl Display for Dockerfile {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let instructions = self
-            .instructions
-            .iter()
-            .map(|i| i.to_string())
-            .collect::<Vec<String>>();
-        write!(f, "{}", instructions.join("\n"))
+        write!(f, "{}", self.render_with(&RenderOptions::default()))
     }
 }
 
@@ -144,8 +949,8 @@ impl Display for Dockerfile {
 mod tests {
     use super::*;
     use crate::{
-        instruction::{EXPOSE, FROM, RUN},
-        instruction_builder::ExposeBuilder,
+        instruction::{ARG, COPY, ENV, EXPOSE, FROM, RUN, WORKDIR},
+        instruction_builder::{ExposeBuilder, Protocol, RunBuilder},
     };
     use expect_test::expect;
 
@@ -173,7 +978,7 @@ mod tests {
         // Use a builder
         let expose_from_builder = ExposeBuilder::builder()
             .port(80)
-            .protocol("tcp")
+            .protocol(Protocol::Tcp)
             .build()
             .unwrap();
 
@@ -204,4 +1009,510 @@ mod tests {
             RUN cargo run"#]];
         expected.assert_eq(&dockerfile.to_string());
     }
+
+    #[test]
+    fn len() {
+        let dockerfile = Dockerfile::default().syntax("docker/dockerfile:1");
+        assert!(dockerfile.is_empty());
+        assert_eq!(dockerfile.len(), 0);
+
+        let dockerfile = dockerfile.push(FROM::from("alpine")).push_any("# comment");
+        assert!(!dockerfile.is_empty());
+        assert_eq!(dockerfile.len(), 2);
+    }
+
+    #[test]
+    fn remove_insert() {
+        let mut dockerfile = Dockerfile::default().push(FROM::from("alpine"));
+        dockerfile.insert(1, RUN::from("apk add curl"));
+        dockerfile.insert(0, "# base");
+        assert_eq!(
+            dockerfile.remove(2),
+            Some(Instruction::from(RUN::from("apk add curl")))
+        );
+        assert_eq!(dockerfile.remove(2), None);
+        assert_eq!(dockerfile.to_string(), "# base\nFROM alpine");
+    }
+
+    #[test]
+    #[should_panic]
+    fn insert_out_of_bounds() {
+        let mut dockerfile = Dockerfile::default();
+        dockerfile.insert(1, "# comment");
+    }
+
+    #[test]
+    fn substitute() {
+        let vars = HashMap::from([("VERSION", "1.70"), ("APP", "server")]);
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("rust:{{VERSION}} AS builder"))
+            .push(RUN::from("cargo build --bin {{APP}} && strip {{APP}}"))
+            .push_any("# {{UNKNOWN}}")
+            .substitute(&vars);
+        let expected = expect![[r#"
+            FROM rust:1.70 AS builder
+            RUN cargo build --bin server && strip server
+            # {{UNKNOWN}}"#]];
+        expected.assert_eq(&dockerfile.to_string());
+    }
+
+    #[test]
+    fn substitute_chained_values() {
+        let vars = HashMap::from([("A", "{{B}}"), ("B", "x")]);
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("img:{{A}}"))
+            .push(RUN::from("echo {{B}}{{A}} {{C}}"))
+            .substitute(&vars);
+        let expected = expect![[r#"
+            FROM img:{{B}}
+            RUN echo x{{B}} {{C}}"#]];
+        expected.assert_eq(&dockerfile.to_string());
+    }
+
+    #[test]
+    fn iterate() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .push(RUN::from("echo $HOME"));
+
+        let mut keywords = vec![];
+        for instruction in &dockerfile {
+            keywords.push(instruction.to_string());
+        }
+        assert_eq!(keywords, vec!["FROM alpine", "RUN echo $HOME"]);
+        assert_eq!(dockerfile.iter().count(), 2);
+
+        let instructions = dockerfile.clone().into_iter().collect::<Vec<_>>();
+        assert_eq!(instructions, dockerfile.into_inner());
+    }
+
+    #[test]
+    fn generated_header() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .with_generated_header("gen", None)
+            .escape_char('`')
+            .unwrap();
+        let expected = expect![[r#"
+            # escape=`
+            # This file is generated by gen, do not edit it manually.
+            FROM alpine"#]];
+        expected.assert_eq(&dockerfile.to_string());
+
+        let dockerfile =
+            "# syntax=docker/dockerfile:1\n# check=skip=JSONArgsRecommended\nFROM alpine"
+                .parse::<Dockerfile>()
+                .unwrap()
+                .with_generated_header("gen", Some("2023-06-01T00:00:00Z"));
+        let expected = expect![[r#"
+            # syntax=docker/dockerfile:1
+            # check=skip=JSONArgsRecommended
+            # This file is generated by gen, do not edit it manually.
+            # Generated at 2023-06-01T00:00:00Z
+            FROM alpine"#]];
+        expected.assert_eq(&dockerfile.to_string());
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_out_of_bounds() {
+        let dockerfile = Dockerfile::default().push(FROM::from("alpine"));
+        let _ = &dockerfile[1];
+    }
+
+    #[test]
+    fn merge() {
+        let base = Dockerfile::default()
+            .syntax("docker/dockerfile:1")
+            .push(FROM::from("alpine"));
+        let app = Dockerfile::default()
+            .syntax("docker/dockerfile:1.4")
+            .push(RUN::from("apk add curl"))
+            .escape_char('`')
+            .unwrap();
+        let mut merged = base + app;
+        merged.extend([Instruction::from(EXPOSE::from("80"))]);
+        let expected = expect![[r#"
+            # syntax=docker/dockerfile:1
+            # escape=`
+            FROM alpine
+            RUN apk add curl
+            EXPOSE 80"#]];
+        expected.assert_eq(&merged.to_string());
+    }
+
+    #[test]
+    fn strip_comments() {
+        let dockerfile = Dockerfile::default()
+            .syntax("docker/dockerfile:1")
+            .escape("`")
+            .push_any("# regular comment")
+            .comment("build stage")
+            .push(FROM::from("alpine"))
+            .push(
+                RunBuilder::builder()
+                    .command("apk add curl")
+                    .comment("tools")
+                    .build()
+                    .unwrap(),
+            )
+            .push_any("#")
+            .strip_comments();
+        let expected = expect![[r#"
+            # syntax=docker/dockerfile:1
+            # escape=`
+            FROM alpine
+            RUN apk add curl"#]];
+        expected.assert_eq(&dockerfile.to_string());
+
+        let dockerfile = "# check=error=true\n# base image\nFROM alpine"
+            .parse::<Dockerfile>()
+            .unwrap()
+            .strip_comments();
+        assert_eq!(dockerfile.to_string(), "# check=error=true\nFROM alpine");
+    }
+
+    #[test]
+    fn entrypoint_with_default_cmd() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .entrypoint_with_default_cmd(vec!["/bin/app"], vec!["--verbose"])
+            .unwrap();
+        let expected = expect![[r#"
+            FROM alpine
+            ENTRYPOINT ["/bin/app"]
+            CMD ["--verbose"]"#]];
+        expected.assert_eq(&dockerfile.to_string());
+
+        let err = Dockerfile::default()
+            .entrypoint_with_default_cmd(Vec::<String>::new(), vec!["--verbose"])
+            .unwrap_err();
+        let expected = expect!["ENTRYPOINT cannot be empty"];
+        expected.assert_eq(&err.to_string());
+
+        let err = Dockerfile::default()
+            .entrypoint_with_default_cmd(vec!["/bin/app"], Vec::<String>::new())
+            .unwrap_err();
+        let expected = expect!["CMD cannot be empty"];
+        expected.assert_eq(&err.to_string());
+    }
+
+    #[test]
+    fn enable_inline_cache() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("rust"))
+            .enable_inline_cache()
+            .enable_inline_cache();
+        let expected = expect![[r#"
+            FROM rust
+            ARG BUILDKIT_INLINE_CACHE=1"#]];
+        expected.assert_eq(&dockerfile.to_string());
+    }
+
+    #[test]
+    fn insert_after_from() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("rust AS builder"))
+            .push(RUN::from("cargo build"))
+            .push(FROM::from("debian AS runtime"))
+            .push(RUN::from("apt-get update"))
+            .insert_after_from("Runtime", ENV::from("DEBIAN_FRONTEND=noninteractive"))
+            .insert_after_from("missing", ENV::from("UNUSED=1"));
+        let expected = expect![[r#"
+            FROM rust AS builder
+            RUN cargo build
+            FROM debian AS runtime
+            ENV DEBIAN_FRONTEND=noninteractive
+            RUN apt-get update"#]];
+        expected.assert_eq(&dockerfile.to_string());
+    }
+
+    #[test]
+    fn new() {
+        assert_eq!(Dockerfile::new(), Dockerfile::default());
+        let dockerfile = Dockerfile::with_capacity(16);
+        assert!(dockerfile.is_empty());
+        assert!(dockerfile.instructions.capacity() >= 16);
+        assert_eq!(dockerfile, Dockerfile::default());
+    }
+
+    #[test]
+    fn env_vars() {
+        let envs = BTreeMap::from([("A", "1"), ("B", "two words"), ("C", "3")]);
+        let combined = Dockerfile::default().env_vars(envs.clone(), true).unwrap();
+        assert_eq!(combined.len(), 1);
+        let split = Dockerfile::default().env_vars(envs, false).unwrap();
+        assert_eq!(split.len(), 3);
+        let expected = expect![[r#"
+            ENV A=1
+            ENV B="two words"
+            ENV C=3"#]];
+        expected.assert_eq(&split.to_string());
+
+        let empty = Dockerfile::default()
+            .env_vars(BTreeMap::<String, String>::new(), true)
+            .unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn with_source_date_epoch() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .with_source_date_epoch(Some(0));
+        let expected = expect![[r#"
+            FROM alpine
+            ARG SOURCE_DATE_EPOCH=0"#]];
+        expected.assert_eq(&dockerfile.to_string());
+        assert_eq!(dockerfile.build_args()[0].default, Some("0".to_string()));
+    }
+
+    #[test]
+    fn sample_all() {
+        let expected = expect![[r#"
+            # syntax=docker/dockerfile:1
+            # sample of every instruction
+            ARG RUST_VERSION=1.70
+            FROM rust:${RUST_VERSION} AS builder
+            MAINTAINER Jane Doe <jane@example.com>
+            LABEL org.opencontainers.image.title="sample app"
+            ENV RUST_LOG=info
+            SHELL ["/bin/bash", "-o", "pipefail", "-c"]
+            WORKDIR /app
+            ADD --checksum=sha256:abc https://example.com/data.tar.gz /data/
+            COPY --chown=1000:1000 . .
+            RUN cargo build --release
+            FROM debian:bookworm-slim
+            COPY --from=builder /app/target/release/app /usr/local/bin/app
+            USER 1000:1000
+            VOLUME ["/data"]
+            EXPOSE 8080/tcp
+            ONBUILD RUN echo triggered
+            STOPSIGNAL SIGTERM
+            HEALTHCHECK --interval=30s CMD curl -f http://localhost:8080/
+            ENTRYPOINT ["app"]
+            CMD ["--port", "8080"]
+            # raw line"#]];
+        expected.assert_eq(&Dockerfile::sample_all().to_string());
+
+        let mut keywords = Dockerfile::sample_all()
+            .iter()
+            .filter_map(Instruction::keyword)
+            .collect::<Vec<_>>();
+        keywords.sort_unstable();
+        keywords.dedup();
+        assert_eq!(keywords.len(), 18, "every instruction is sampled");
+    }
+
+    #[test]
+    fn inspect() {
+        let counts = std::cell::RefCell::new(vec![]);
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .inspect(|d| counts.borrow_mut().push(d.instructions.len()))
+            .push(RUN::from("echo $HOME"))
+            .inspect(|d| counts.borrow_mut().push(d.instructions.len()));
+
+        assert_eq!(counts.into_inner(), vec![1, 2]);
+        assert_eq!(dockerfile.into_inner().len(), 2);
+    }
+
+    #[test]
+    fn maintainer() {
+        let dockerfile = Dockerfile::default().maintainer("Phuong Pham <ptmphuong9@gmail.com>");
+        let expected = expect![[
+            r#"LABEL org.opencontainers.image.authors="Phuong Pham <ptmphuong9@gmail.com>""#
+        ]];
+        expected.assert_eq(&dockerfile.to_string());
+        assert!(!dockerfile.to_string().contains("MAINTAINER"));
+
+        let dockerfile = Dockerfile::default().maintainer(r"Jane\");
+        let expected = expect![[r#"LABEL org.opencontainers.image.authors="Jane\\""#]];
+        expected.assert_eq(&dockerfile.to_string());
+        let dockerfile = Dockerfile::default().maintainer(r#"Jane "JD" Doe"#);
+        let expected = expect![[r#"LABEL org.opencontainers.image.authors="Jane \"JD\" Doe""#]];
+        expected.assert_eq(&dockerfile.to_string());
+    }
+
+    #[test]
+    fn normalized_eq() {
+        let a = Dockerfile::default()
+            .push(FROM::from("alpine "))
+            .push(RUN::from("echo $HOME"));
+        let b = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .push(RUN::from("echo $HOME  "));
+        assert!(a.normalized_eq(&b));
+
+        let c = Dockerfile::default().push(FROM::from("alpine"));
+        assert!(!a.normalized_eq(&c));
+        let d = Dockerfile::default()
+            .push(FROM::from(" alpine"))
+            .push(RUN::from("echo $HOME"));
+        assert!(!a.normalized_eq(&d));
+    }
+
+    #[test]
+    fn normalized_eq_ignores_comment() {
+        let commented = FROM {
+            comment: Some("base image".to_string()),
+            ..FROM::from("alpine")
+        };
+        let a = Dockerfile::default().push(commented);
+        let b = Dockerfile::default().push(FROM::from("alpine"));
+        assert_ne!(a, b);
+        assert!(a.normalized_eq(&b));
+
+        // Standalone comments are still compared
+        let c = Dockerfile::default()
+            .comment("base image")
+            .push(FROM::from("alpine"));
+        assert!(!c.normalized_eq(&b));
+    }
+
+    #[test]
+    fn write_to_file() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .push(RUN::from("echo $HOME"));
+        let path = std::env::temp_dir().join(format!("Dockerfile.{}", std::process::id()));
+        dockerfile.write_to_file(&path).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let expected = expect![[r#"
+            FROM alpine
+            RUN echo $HOME
+        "#]];
+        expected.assert_eq(&content);
+    }
+
+    #[test]
+    fn pin_from_digests() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from(
+                "--platform=linux/amd64 localhost:5000/rust:1.70 AS builder",
+            ))
+            .push(FROM::from("builder"))
+            .push(FROM::from("alpine@sha256:old"))
+            .push(FROM::from("scratch"))
+            .push(FROM::from("debian"))
+            .push(FROM::from("unknown:1"))
+            .pin_from_digests(|image| match image {
+                "unknown:1" => None,
+                _ => Some(format!("sha256:{}", image.len())),
+            });
+
+        let expected = expect![[r#"
+            FROM --platform=linux/amd64 localhost:5000/rust@sha256:24 AS builder
+            FROM builder
+            FROM alpine@sha256:old
+            FROM scratch
+            FROM debian@sha256:6
+            FROM unknown:1"#]];
+        expected.assert_eq(&dockerfile.to_string());
+    }
+
+    #[test]
+    fn pin_from_digests_resolves_pinnable_images_only() {
+        let resolved = std::cell::RefCell::new(vec![]);
+        Dockerfile::default()
+            .push(FROM::from("rust:1.70 AS builder"))
+            .push(FROM::from("builder"))
+            .push(FROM::from("scratch"))
+            .push(FROM::from("alpine@sha256:old"))
+            .pin_from_digests(|image| {
+                resolved.borrow_mut().push(image.to_string());
+                None
+            });
+        assert_eq!(resolved.into_inner(), vec!["rust:1.70"]);
+    }
+
+    #[test]
+    fn pin_from_digests_keeps_arg_images() {
+        let dockerfile = Dockerfile::default()
+            .push(ARG::from("BASE=alpine"))
+            .push(FROM::from("${BASE}  AS  base"))
+            .push(FROM::from(
+                "--platform=$BUILDPLATFORM  rust:1.70   AS builder",
+            ))
+            .pin_from_digests(|image| {
+                assert!(!image.contains('$'), "resolver called with {}", image);
+                Some("sha256:abc".to_string())
+            });
+        let expected = expect![[r#"
+            ARG BASE=alpine
+            FROM ${BASE}  AS  base
+            FROM --platform=$BUILDPLATFORM  rust@sha256:abc   AS builder"#]];
+        expected.assert_eq(&dockerfile.to_string());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .push(
+                ExposeBuilder::builder()
+                    .comment("web")
+                    .port(80)
+                    .build()
+                    .unwrap(),
+            )
+            .push_any("# done");
+
+        let json = serde_json::to_string(&dockerfile).unwrap();
+        let expected = expect![[
+            r##"{"instructions":[{"FROM":{"value":"alpine"}},{"EXPOSE":{"value":"80","comment":"web"}},{"ANY":"# done"}]}"##
+        ]];
+        expected.assert_eq(&json);
+
+        let deserialized: Dockerfile = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, dockerfile);
+    }
+
+    #[test]
+    fn directives() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .escape_char('`')
+            .unwrap()
+            .syntax("docker/dockerfile:1.4")
+            .syntax("docker/dockerfile:1");
+        let expected = expect![[r#"
+            # syntax=docker/dockerfile:1
+            # escape=`
+            FROM alpine"#]];
+        expected.assert_eq(&dockerfile.to_string());
+    }
+
+    #[test]
+    fn escape_char() {
+        let dockerfile = Dockerfile::default()
+            .push_any("# comment \\")
+            .push(FROM::from("alpine"))
+            .push(RUN::from("echo a \\\n  && echo b \\\n  && echo c"))
+            .push(RUN::from("<<EOF\necho \\\nEOF"))
+            .push(WORKDIR::from("C:\\"))
+            .push(COPY::from(". C:\\app\\"))
+            .escape_char('`')
+            .unwrap();
+        let expected = expect![[r#"
+            # escape=`
+            # comment \
+            FROM alpine
+            RUN echo a `
+              && echo b `
+              && echo c
+            RUN <<EOF
+            echo \
+            EOF
+            WORKDIR C:\
+            COPY . C:\app\"#]];
+        expected.assert_eq(&dockerfile.to_string());
+
+        let dockerfile = dockerfile.escape_char('\\').unwrap();
+        assert!(dockerfile.to_string().contains("echo a \\\n"));
+        assert!(Dockerfile::default().escape_char('"').is_err());
+    }
 }
@@ -0,0 +1,1057 @@
+//! Lints for [`Dockerfile`]
+//!
+//! Lints never change the rendered Dockerfile, they only report likely mistakes.
+//! Heuristic lints are opt-in and can be enabled with [`LintConfig`].
+//!
+//! ```
+//! use dockerfile_builder::Dockerfile;
+//! use dockerfile_builder::instruction::COPY;
+//! use dockerfile_builder::lint::{LintConfig, LintWarning};
+//!
+//! let dockerfile = Dockerfile::default()
+//!     .push(COPY::from("--from=node:18 --chown=node /app /app"));
+//!
+//! let config = LintConfig {
+//!     chown_name_with_from: true,
+//!     ..Default::default()
+//! };
+//! assert_eq!(
+//!     dockerfile.lint_with(&config),
+//!     vec![LintWarning::ChownNameWithFrom { index: 0, chown: "node".to_string() }],
+//! );
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+
+use crate::{
+    analysis::{exec_form, split_words, stage_ranges},
+    instruction::Instruction,
+    Dockerfile,
+};
+
+/// Warnings reported by [`Dockerfile::lint`] and [`Dockerfile::lint_with`]
+///
+/// `index` is the position of the offending instruction in the Dockerfile.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LintWarning {
+    /// `COPY --from=<image> --chown=<name>` uses a user or group name, which fails at build time
+    /// if the name doesn't exist in the current stage
+    ChownNameWithFrom { index: usize, chown: String },
+    /// `${name}` references a variable that isn't declared by a preceding `ARG` or `ENV` in
+    /// scope, so it expands to an empty string
+    UndefinedVariable { index: usize, name: String },
+    /// `COPY` or `ADD` of a path that looks like it contains secrets
+    SensitiveFile { index: usize, path: String },
+    /// Exec form `CMD` with only parameters, e.g. `CMD ["-l", "8000"]`, in a stage without
+    /// `ENTRYPOINT`
+    CmdParamsWithoutEntrypoint { index: usize },
+    /// Exec form `RUN`, `CMD` or `ENTRYPOINT` with an element containing shell syntax, like
+    /// `$(...)`, `${...}`, `|` or `>`, which isn't interpreted without a shell
+    ShellSyntaxInExecForm { index: usize, word: String },
+    /// `CMD`, `ENTRYPOINT` or `HEALTHCHECK` overridden by a later one in the same stage, only
+    /// the last one takes effect
+    OverriddenInstruction { index: usize, keyword: String },
+    /// `RUN` installs packages without a non-interactive flag, e.g. `apt-get install` without
+    /// `-y`, so the build waits for a confirmation that never comes
+    MissingAssumeYes { index: usize, command: String },
+    /// `EXPOSE` in a stage without `CMD` or `ENTRYPOINT`, so nothing in the stage appears to
+    /// start the service listening on the port
+    ExposeWithoutCommand { index: usize },
+    /// `COPY` or `ADD` of the whole build context, e.g. `COPY . .`, which also copies files
+    /// like `.git` or build outputs unless they're excluded by a `.dockerignore`
+    WholeContextCopy { index: usize },
+    /// `RUN` command matching a pattern of [`LintConfig::non_deterministic_run`], e.g. piping a
+    /// downloaded script to a shell, which may build something different every time
+    NonDeterministicRun { index: usize, command: String },
+}
+
+impl Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintWarning::ChownNameWithFrom { index, chown } => write!(
+                f,
+                "instruction {}: --chown={} with --from may fail if the user or group doesn't exist in this stage",
+                index, chown
+            ),
+            LintWarning::UndefinedVariable { index, name } => write!(
+                f,
+                "instruction {}: ${{{}}} is not declared by a preceding ARG or ENV",
+                index, name
+            ),
+            LintWarning::SensitiveFile { index, path } => write!(
+                f,
+                "instruction {}: {} looks like a secret and would be stored in the image",
+                index, path
+            ),
+            LintWarning::CmdParamsWithoutEntrypoint { index } => write!(
+                f,
+                "instruction {}: CMD only has parameters but there is no ENTRYPOINT in this stage",
+                index
+            ),
+            LintWarning::ShellSyntaxInExecForm { index, word } => write!(
+                f,
+                "instruction {}: {} contains shell syntax, which isn't interpreted in exec form",
+                index, word
+            ),
+            LintWarning::OverriddenInstruction { index, keyword } => write!(
+                f,
+                "instruction {}: {} is overridden by a later {} in the same stage",
+                index, keyword, keyword
+            ),
+            LintWarning::MissingAssumeYes { index, command } => write!(
+                f,
+                "instruction {}: {} needs a non-interactive flag to not wait for confirmation",
+                index, command
+            ),
+            LintWarning::ExposeWithoutCommand { index } => write!(
+                f,
+                "instruction {}: EXPOSE in a stage without CMD or ENTRYPOINT",
+                index
+            ),
+            LintWarning::WholeContextCopy { index } => write!(
+                f,
+                "instruction {}: copies the whole build context, exclude unneeded files with a .dockerignore",
+                index
+            ),
+            LintWarning::NonDeterministicRun { index, command } => write!(
+                f,
+                "instruction {}: {} may not be reproducible, pin a version or ref",
+                index, command
+            ),
+        }
+    }
+}
+
+/// Configuration for [`Dockerfile::lint_with`]
+///
+/// All heuristic lints are disabled by default.
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    /// Warn on `COPY --from` combined with a non-numeric `--chown`
+    pub chown_name_with_from: bool,
+    /// Warn on `${VAR}` references to variables that aren't declared by a preceding `ARG` or
+    /// `ENV`. Variables set by the base image, like `PATH`, can't be known and are reported too.
+    pub undefined_variable: bool,
+    /// Warn on `COPY` or `ADD` paths matching one of these patterns. Patterns match the end of
+    /// a path and `*` matches any part of a path segment, e.g. `.aws/credentials` or `*.pem`.
+    /// [`SENSITIVE_FILE_PATTERNS`] is a good starting point.
+    pub sensitive_files: Option<Vec<String>>,
+    /// Warn on exec form `CMD`s whose first element starts with `-`, in a stage without
+    /// `ENTRYPOINT`. An `ENTRYPOINT` inherited from a previous stage counts, one inherited from
+    /// the base image can't be known and is reported too.
+    pub cmd_params_without_entrypoint: bool,
+    /// Warn on exec form `RUN`, `CMD` and `ENTRYPOINT` elements containing shell syntax, unless
+    /// the executable is a shell run with `-c`
+    pub shell_syntax_in_exec_form: bool,
+    /// Warn on `CMD`, `ENTRYPOINT` and `HEALTHCHECK` overridden by a later one in the same
+    /// stage
+    pub overridden_instructions: bool,
+    /// Warn on `RUN` commands installing packages with `apt-get`, `apt`, `yum`, `dnf` or `apk`
+    /// without a non-interactive flag. It's a heuristic: the package manager is matched
+    /// anywhere in a command, e.g. in `echo apt install`, and `apk add` never prompts, it's
+    /// only reported without `--no-cache`.
+    pub missing_assume_yes: bool,
+    /// Warn on `EXPOSE` in a stage without `CMD` or `ENTRYPOINT`. A command inherited from the
+    /// base image can't be known and is reported too.
+    pub expose_without_command: bool,
+    /// Warn on `COPY` and `ADD` of the whole build context, e.g. `COPY . .`. The
+    /// `.dockerignore` isn't part of the Dockerfile, so it's reported even if one exists.
+    pub whole_context_copy: bool,
+    /// Warn on `RUN` commands matching one of these patterns. Each command of a `RUN`, split on
+    /// `&&`, `||`, `;` and newlines, is matched as a whole and `*` matches any text, e.g.
+    /// `curl * | sh`. [`NON_DETERMINISTIC_RUN_PATTERNS`] is a good starting point.
+    pub non_deterministic_run: Option<Vec<String>>,
+}
+
+/// Default patterns for [`LintConfig::sensitive_files`]
+pub const SENSITIVE_FILE_PATTERNS: &[&str] = &[
+    ".env",
+    ".env.*",
+    "id_rsa",
+    "id_dsa",
+    "id_ecdsa",
+    "id_ed25519",
+    "*.pem",
+    "*.key",
+    ".aws/credentials",
+    ".docker/config.json",
+    ".git-credentials",
+    ".netrc",
+    ".npmrc",
+    ".pypirc",
+];
+
+/// Default patterns for [`LintConfig::non_deterministic_run`]
+///
+/// They're coarse: installing pinned versions, e.g. `pip install flask==2.3.2`, matches too.
+pub const NON_DETERMINISTIC_RUN_PATTERNS: &[&str] = &[
+    "curl * | sh*",
+    "curl * | bash*",
+    "wget * | sh*",
+    "wget * | bash*",
+    "git clone *",
+    "pip install *",
+    "pip3 install *",
+    "npm install -g *",
+    "go install *@latest",
+];
+
+impl Dockerfile {
+    /// Runs the default lints
+    ///
+    /// Every lint is currently opt-in, enable them with [`Dockerfile::lint_with`].
+    pub fn lint(&self) -> Vec<LintWarning> {
+        self.lint_with(&LintConfig::default())
+    }
+
+    /// Runs the default lints and the lints enabled in `config`
+    pub fn lint_with(&self, config: &LintConfig) -> Vec<LintWarning> {
+        let mut warnings = vec![];
+        for (index, instruction) in self.instructions.iter().enumerate() {
+            if let Instruction::COPY(copy) = instruction {
+                if config.chown_name_with_from {
+                    warnings.extend(chown_name_with_from(index, &copy.value));
+                }
+            }
+            if let Instruction::RUN(_) | Instruction::CMD(_) | Instruction::ENTRYPOINT(_) =
+                instruction
+            {
+                if config.shell_syntax_in_exec_form {
+                    warnings.extend(shell_syntax_in_exec_form(index, instruction.value()));
+                }
+            }
+            if let Instruction::RUN(run) = instruction {
+                if config.missing_assume_yes {
+                    warnings.extend(missing_assume_yes(index, &run.value));
+                }
+                if let Some(patterns) = &config.non_deterministic_run {
+                    warnings.extend(non_deterministic_run(index, &run.value, patterns));
+                }
+            }
+            if let (Instruction::COPY(_) | Instruction::ADD(_), Some(patterns)) =
+                (instruction, &config.sensitive_files)
+            {
+                warnings.extend(sensitive_files(index, instruction.value(), patterns));
+            }
+            if let Instruction::COPY(_) | Instruction::ADD(_) = instruction {
+                if config.whole_context_copy {
+                    warnings.extend(whole_context_copy(index, instruction.value()));
+                }
+            }
+        }
+        if config.overridden_instructions {
+            warnings.extend(overridden_instructions(&self.instructions));
+        }
+        if config.undefined_variable {
+            warnings.extend(undefined_variables(&self.instructions));
+        }
+        if config.cmd_params_without_entrypoint {
+            warnings.extend(cmd_params_without_entrypoint(&self.instructions));
+        }
+        if config.expose_without_command {
+            warnings.extend(expose_without_command(&self.instructions));
+        }
+        warnings
+    }
+}
+
+fn chown_name_with_from(index: usize, value: &str) -> Option<LintWarning> {
+    flag(value, "from")?;
+    let chown = flag(value, "chown")?;
+    let is_numeric = chown
+        .split(':')
+        .all(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()));
+    if is_numeric {
+        return None;
+    }
+    Some(LintWarning::ChownNameWithFrom {
+        index,
+        chown: chown.to_string(),
+    })
+}
+
+fn sensitive_files(index: usize, value: &str, patterns: &[String]) -> Vec<LintWarning> {
+    split_words(value)
+        .iter()
+        .skip_while(|word| word.starts_with("--"))
+        .map(|word| word.trim_matches(|c| matches!(c, '[' | ']' | ',' | '"')))
+        .filter(|path| {
+            patterns
+                .iter()
+                .any(|pattern| matches_path_pattern(path, pattern))
+        })
+        .map(|path| LintWarning::SensitiveFile {
+            index,
+            path: path.to_string(),
+        })
+        .collect()
+}
+
+fn whole_context_copy(index: usize, value: &str) -> Option<LintWarning> {
+    if flag(value, "from").is_some() {
+        return None;
+    }
+    let words = split_words(value);
+    let paths = words
+        .iter()
+        .skip_while(|word| word.starts_with("--"))
+        .collect::<Vec<_>>();
+    let (_, sources) = paths.split_last()?;
+    sources
+        .iter()
+        .any(|source| matches!(source.as_str(), "." | "./"))
+        .then_some(LintWarning::WholeContextCopy { index })
+}
+
+fn non_deterministic_run(index: usize, value: &str, patterns: &[String]) -> Vec<LintWarning> {
+    value
+        .split(['\n', ';'])
+        .flat_map(|command| command.split("&&"))
+        .flat_map(|command| command.split("||"))
+        .map(str::trim)
+        .filter(|command| {
+            patterns
+                .iter()
+                .any(|pattern| matches_glob(command, pattern))
+        })
+        .map(|command| LintWarning::NonDeterministicRun {
+            index,
+            command: command.to_string(),
+        })
+        .collect()
+}
+
+/// Matches the trailing segments of `path` against `pattern`, segment by segment
+fn matches_path_pattern(path: &str, pattern: &str) -> bool {
+    let path = path.trim_end_matches('/');
+    let segments = path.split('/').collect::<Vec<_>>();
+    let pattern_segments = pattern.split('/').collect::<Vec<_>>();
+    if pattern_segments.len() > segments.len() {
+        return false;
+    }
+    segments[segments.len() - pattern_segments.len()..]
+        .iter()
+        .zip(&pattern_segments)
+        .all(|(segment, pattern)| matches_glob(segment, pattern))
+}
+
+/// Matches `text` against a pattern where `*` matches any sequence of characters
+fn matches_glob(text: &str, pattern: &str) -> bool {
+    match pattern.split_once('*') {
+        None => text == pattern,
+        Some((prefix, rest)) => {
+            let Some(text) = text.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=text.len())
+                .filter(|i| text.is_char_boundary(*i))
+                .any(|i| matches_glob(&text[i..], rest))
+        }
+    }
+}
+
+fn overridden_instructions(instructions: &[Instruction]) -> Vec<LintWarning> {
+    let keyword = |instruction: &Instruction| match instruction {
+        Instruction::CMD(_) => Some("CMD"),
+        Instruction::ENTRYPOINT(_) => Some("ENTRYPOINT"),
+        Instruction::HEALTHCHECK(_) => Some("HEALTHCHECK"),
+        _ => None,
+    };
+    let mut warnings = vec![];
+    for stage in stage_ranges(instructions) {
+        for index in stage.clone() {
+            let Some(current) = keyword(&instructions[index]) else {
+                continue;
+            };
+            let overridden = instructions[index + 1..stage.end]
+                .iter()
+                .any(|later| keyword(later) == Some(current));
+            if overridden {
+                warnings.push(LintWarning::OverriddenInstruction {
+                    index,
+                    keyword: current.to_string(),
+                });
+            }
+        }
+    }
+    warnings
+}
+
+/// Shell syntax that only works in shell form
+const SHELL_SYNTAX: &[&str] = &["$(", "${", "`", "|", ">", "<", "&&", ";"];
+
+/// Executables that interpret shell syntax in their `-c` argument
+const SHELLS: &[&str] = &["sh", "bash", "ash", "dash", "zsh"];
+
+fn shell_syntax_in_exec_form(index: usize, value: &str) -> Option<LintWarning> {
+    let words = exec_form(value)?;
+    let executable = words.first()?.rsplit('/').next().unwrap_or_default();
+    if SHELLS.contains(&executable) && words.iter().any(|w| w == "-c") {
+        return None;
+    }
+    let word = words
+        .into_iter()
+        .find(|word| SHELL_SYNTAX.iter().any(|syntax| word.contains(syntax)))?;
+    Some(LintWarning::ShellSyntaxInExecForm { index, word })
+}
+
+/// Package manager commands with the flags that make them non-interactive
+const PACKAGE_INSTALLS: &[(&str, &str, &[&str])] = &[
+    ("apt-get", "install", &["-y", "--yes", "--assume-yes"]),
+    ("apt", "install", &["-y", "--yes", "--assume-yes"]),
+    ("yum", "install", &["-y", "--assumeyes"]),
+    ("dnf", "install", &["-y", "--assumeyes"]),
+    ("apk", "add", &["--no-cache"]),
+];
+
+fn missing_assume_yes(index: usize, value: &str) -> Vec<LintWarning> {
+    let words = exec_form(value).unwrap_or_else(|| split_words(value));
+    let mut commands = vec![vec![]];
+    for word in &words {
+        match word.as_str() {
+            "&&" | "||" | "|" | ";" => commands.push(vec![]),
+            word => {
+                let command = commands.last_mut().expect("commands is never empty");
+                command.push(word.trim_end_matches(';'));
+                if word.ends_with(';') {
+                    commands.push(vec![]);
+                }
+            }
+        }
+    }
+    let mut warnings = vec![];
+    for command in commands {
+        for (manager, subcommand, flags) in PACKAGE_INSTALLS {
+            let Some(position) = command.iter().position(|w| w == manager) else {
+                continue;
+            };
+            let args = &command[position + 1..];
+            if !args.contains(subcommand) {
+                continue;
+            }
+            let non_interactive = args.iter().any(|arg| {
+                flags.contains(arg)
+                    || (flags.contains(&"-y")
+                        && arg.starts_with('-')
+                        && !arg.starts_with("--")
+                        && arg.contains('y'))
+            });
+            if !non_interactive {
+                warnings.push(LintWarning::MissingAssumeYes {
+                    index,
+                    command: format!("{} {}", manager, subcommand),
+                });
+            }
+        }
+    }
+    warnings
+}
+
+/// Each stage inherits the `ENTRYPOINT` of the stage it's built from, if any
+fn cmd_params_without_entrypoint(instructions: &[Instruction]) -> Vec<LintWarning> {
+    let mut warnings = vec![];
+    let mut stage_entrypoints: HashMap<String, bool> = HashMap::new();
+    for stage in stage_ranges(instructions) {
+        let (image, name) = match &instructions[stage.start] {
+            Instruction::FROM(from) => parse_from(&from.value),
+            _ => ("", None),
+        };
+        let inherited = stage_entrypoints
+            .get(&image.to_lowercase())
+            .copied()
+            .unwrap_or(false);
+        let has_entrypoint = inherited
+            || instructions[stage.clone()]
+                .iter()
+                .any(|i| matches!(i, Instruction::ENTRYPOINT(_)));
+        if let Some(name) = name {
+            stage_entrypoints.insert(name.to_lowercase(), has_entrypoint);
+        }
+        if has_entrypoint {
+            continue;
+        }
+        for index in stage {
+            let Instruction::CMD(cmd) = &instructions[index] else {
+                continue;
+            };
+            let params_only = exec_form(&cmd.value)
+                .and_then(|words| words.first().map(|w| w.starts_with('-')))
+                .unwrap_or(false);
+            if params_only {
+                warnings.push(LintWarning::CmdParamsWithoutEntrypoint { index });
+            }
+        }
+    }
+    warnings
+}
+
+fn expose_without_command(instructions: &[Instruction]) -> Vec<LintWarning> {
+    let mut warnings = vec![];
+    for stage in stage_ranges(instructions) {
+        let has_command = instructions[stage.clone()]
+            .iter()
+            .any(|i| matches!(i, Instruction::CMD(_) | Instruction::ENTRYPOINT(_)));
+        if has_command {
+            continue;
+        }
+        warnings.extend(
+            stage
+                .filter(|&index| matches!(instructions[index], Instruction::EXPOSE(_)))
+                .map(|index| LintWarning::ExposeWithoutCommand { index }),
+        );
+    }
+    warnings
+}
+
+/// Tracks variables declared by `ARG` and `ENV` in order, following Docker's scoping rules:
+/// * `ARG`s before the first `FROM` are only in scope for `FROM` instructions.
+/// * Each stage starts with the `ENV`s of the stage it's built from, if any.
+fn undefined_variables(instructions: &[Instruction]) -> Vec<LintWarning> {
+    let mut warnings = vec![];
+    let mut global_args = HashSet::new();
+    let mut stage_envs: HashMap<String, HashSet<String>> = HashMap::new();
+    let mut stage_name: Option<String> = None;
+    let mut in_stage = false;
+    let mut envs = HashSet::new();
+    let mut defined = HashSet::new();
+
+    for (index, instruction) in instructions.iter().enumerate() {
+        let (value, declared) = match instruction {
+            Instruction::FROM(from) => {
+                for name in variable_references(&from.value) {
+                    if !global_args.contains(&name) {
+                        warnings.push(LintWarning::UndefinedVariable { index, name });
+                    }
+                }
+                if let Some(name) = stage_name.take() {
+                    stage_envs.insert(name, envs);
+                }
+                let (image, name) = parse_from(&from.value);
+                envs = stage_envs
+                    .get(&image.to_lowercase())
+                    .cloned()
+                    .unwrap_or_default();
+                defined = envs.clone();
+                stage_name = name.map(|n| n.to_lowercase());
+                in_stage = true;
+                continue;
+            }
+            Instruction::ARG(arg) => (&arg.value, arg_names(&arg.value)),
+            Instruction::ENV(env) => (&env.value, env_keys(&env.value)),
+            Instruction::ADD(ins) => (&ins.value, vec![]),
+            Instruction::COPY(ins) => (&ins.value, vec![]),
+            Instruction::EXPOSE(ins) => (&ins.value, vec![]),
+            Instruction::LABEL(ins) => (&ins.value, vec![]),
+            Instruction::STOPSIGNAL(ins) => (&ins.value, vec![]),
+            Instruction::USER(ins) => (&ins.value, vec![]),
+            Instruction::VOLUME(ins) => (&ins.value, vec![]),
+            Instruction::WORKDIR(ins) => (&ins.value, vec![]),
+            _ => continue,
+        };
+
+        let scope = if in_stage { &defined } else { &global_args };
+        for name in variable_references(value) {
+            if !scope.contains(&name) {
+                warnings.push(LintWarning::UndefinedVariable { index, name });
+            }
+        }
+
+        match instruction {
+            Instruction::ARG(_) if !in_stage => global_args.extend(declared),
+            Instruction::ENV(_) => {
+                envs.extend(declared.iter().cloned());
+                defined.extend(declared);
+            }
+            _ => defined.extend(declared),
+        }
+    }
+    warnings
+}
+
+/// Returns the names of plain `${name}` references, skipping escaped `\${name}` and references
+/// with a modifier like `${name:-default}`
+fn variable_references(value: &str) -> Vec<String> {
+    let mut names = vec![];
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let escaped = rest[..start].ends_with('\\');
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        let name = &rest[..end];
+        let is_plain =
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if is_plain && !escaped {
+            names.push(name.to_string());
+        }
+        rest = &rest[end + 1..];
+    }
+    names
+}
+
+/// Returns the names declared by an `ARG <name>[=<default>] ...` value
+fn arg_names(value: &str) -> Vec<String> {
+    value
+        .split_whitespace()
+        .map(|token| token.split('=').next().unwrap_or(token).to_string())
+        .collect()
+}
+
+/// Returns the keys set by an `ENV <key>=<value> ...` or `ENV <key> <value>` value
+fn env_keys(value: &str) -> Vec<String> {
+    let mut tokens = value.split_whitespace();
+    match tokens.next() {
+        Some(first) if !first.contains('=') => vec![first.to_string()],
+        Some(first) => std::iter::once(first)
+            .chain(tokens)
+            .filter_map(|token| token.split_once('='))
+            .map(|(key, _)| key)
+            .filter(|key| {
+                !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            })
+            .map(|key| key.to_string())
+            .collect(),
+        None => vec![],
+    }
+}
+
+/// Returns the image and the optional stage name of a `FROM` value
+pub(crate) fn parse_from(value: &str) -> (&str, Option<&str>) {
+    let mut tokens = value
+        .split_whitespace()
+        .skip_while(|token| token.starts_with("--"));
+    let image = tokens.next().unwrap_or_default();
+    let name = match (tokens.next(), tokens.next()) {
+        (Some(as_keyword), name) if as_keyword.eq_ignore_ascii_case("AS") => name,
+        _ => None,
+    };
+    (image, name)
+}
+
+/// Returns the value of a leading `--<name>=<value>` flag of an instruction value
+pub(crate) fn flag<'a>(value: &'a str, name: &str) -> Option<&'a str> {
+    value
+        .split_whitespace()
+        .take_while(|token| token.starts_with("--"))
+        .find_map(|token| {
+            token[2..]
+                .strip_prefix(name)
+                .and_then(|rest| rest.strip_prefix('='))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction::{
+        ADD, ARG, CMD, COPY, ENTRYPOINT, ENV, EXPOSE, FROM, HEALTHCHECK, LABEL, RUN, WORKDIR,
+    };
+    use crate::instruction_builder::CopyBuilder;
+
+    fn chown_config() -> LintConfig {
+        LintConfig {
+            chown_name_with_from: true,
+            ..Default::default()
+        }
+    }
+
+    fn undefined_variable_config() -> LintConfig {
+        LintConfig {
+            undefined_variable: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn chown_name_with_from() {
+        let copy = CopyBuilder::builder()
+            .from("img")
+            .chown("node")
+            .src("/app")
+            .dest("/app")
+            .build()
+            .unwrap();
+        let dockerfile = Dockerfile::default().push(copy);
+
+        assert_eq!(
+            dockerfile.lint_with(&chown_config()),
+            vec![LintWarning::ChownNameWithFrom {
+                index: 0,
+                chown: "node".to_string(),
+            }],
+        );
+        assert!(dockerfile.lint().is_empty());
+    }
+
+    #[test]
+    fn chown_numeric_with_from() {
+        let dockerfile = Dockerfile::default()
+            .push(COPY::from("--from=img --chown=1000:1000 /app /app"))
+            .push(COPY::from("--chown=node /app /app"));
+        assert!(dockerfile.lint_with(&chown_config()).is_empty());
+    }
+
+    #[test]
+    fn undefined_variable() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .push(ENV::from("VERSION=${BUILD_VERSION}"))
+            .push(ARG::from("BUILD_VERSION"));
+        assert_eq!(
+            dockerfile.lint_with(&undefined_variable_config()),
+            vec![LintWarning::UndefinedVariable {
+                index: 1,
+                name: "BUILD_VERSION".to_string(),
+            }],
+        );
+        assert!(dockerfile.lint().is_empty());
+    }
+
+    #[test]
+    fn undefined_variable_scopes() {
+        let dockerfile = Dockerfile::default()
+            .push(ARG::from("BASE=alpine"))
+            .push(FROM::from("${BASE} AS builder"))
+            .push(ENV::from("APP_DIR=/app"))
+            .push(WORKDIR::from("${APP_DIR}"))
+            .push(LABEL::from("base=${BASE}"))
+            .push(FROM::from("builder"))
+            .push(ARG::from("BASE"))
+            .push(COPY::from("${BASE} ${APP_DIR} ${HOME:-/root} \\${LITERAL}"));
+        assert_eq!(
+            dockerfile.lint_with(&undefined_variable_config()),
+            vec![LintWarning::UndefinedVariable {
+                index: 4,
+                name: "BASE".to_string(),
+            }],
+        );
+    }
+
+    #[test]
+    fn undefined_variable_stage_name_case() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine AS Builder"))
+            .push(ENV::from("APP_DIR=/app"))
+            .push(FROM::from("builder"))
+            .push(WORKDIR::from("${APP_DIR}"));
+        assert!(dockerfile
+            .lint_with(&undefined_variable_config())
+            .is_empty());
+    }
+
+    #[test]
+    fn sensitive_files() {
+        let config = LintConfig {
+            sensitive_files: Some(
+                SENSITIVE_FILE_PATTERNS
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+        let dockerfile = Dockerfile::default()
+            .push(COPY::from(".env /app/"))
+            .push(COPY::from("--chown=1000 src/ /app/"))
+            .push(ADD::from(
+                r#"["/home/me/.aws/credentials", "certs/server.pem", "/root/"]"#,
+            ));
+        assert_eq!(
+            dockerfile.lint_with(&config),
+            vec![
+                LintWarning::SensitiveFile {
+                    index: 0,
+                    path: ".env".to_string(),
+                },
+                LintWarning::SensitiveFile {
+                    index: 2,
+                    path: "/home/me/.aws/credentials".to_string(),
+                },
+                LintWarning::SensitiveFile {
+                    index: 2,
+                    path: "certs/server.pem".to_string(),
+                },
+            ],
+        );
+        assert!(dockerfile.lint().is_empty());
+
+        let config = LintConfig {
+            sensitive_files: Some(vec!["secrets/*".to_string()]),
+            ..Default::default()
+        };
+        let dockerfile = Dockerfile::default()
+            .push(COPY::from(".env /app/"))
+            .push(COPY::from("secrets/token /app/"));
+        assert_eq!(
+            dockerfile.lint_with(&config),
+            vec![LintWarning::SensitiveFile {
+                index: 1,
+                path: "secrets/token".to_string(),
+            }],
+        );
+    }
+
+    #[test]
+    fn cmd_params_without_entrypoint() {
+        let config = LintConfig {
+            cmd_params_without_entrypoint: true,
+            ..Default::default()
+        };
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine AS base"))
+            .push(CMD::from(r#"["-l", "8000"]"#))
+            .push(ENTRYPOINT::from(r#"["nc"]"#))
+            .push(FROM::from("base"))
+            .push(CMD::from(r#"["--port", "80"]"#))
+            .push(CMD::from(r#"["nc", "-l"]"#))
+            .push(CMD::from("-l 80"))
+            .push(FROM::from("alpine"))
+            .push(CMD::from(r#"["--port", "80"]"#));
+        assert_eq!(
+            dockerfile.lint_with(&config),
+            vec![LintWarning::CmdParamsWithoutEntrypoint { index: 8 }],
+        );
+        assert!(dockerfile.lint().is_empty());
+    }
+
+    #[test]
+    fn cmd_params_with_inherited_entrypoint() {
+        let config = LintConfig {
+            cmd_params_without_entrypoint: true,
+            ..Default::default()
+        };
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine AS base"))
+            .push(ENTRYPOINT::from(r#"["nc"]"#))
+            .push(FROM::from("base AS server"))
+            .push(CMD::from(r#"["-l", "8000"]"#))
+            .push(FROM::from("SERVER"))
+            .push(CMD::from(r#"["-l", "9000"]"#));
+        assert!(dockerfile.lint_with(&config).is_empty());
+    }
+
+    #[test]
+    fn shell_syntax_in_exec_form() {
+        let config = LintConfig {
+            shell_syntax_in_exec_form: true,
+            ..Default::default()
+        };
+        let dockerfile = Dockerfile::default()
+            .push(RUN::from(r#"["ps", "aux", "|", "grep", "app"]"#))
+            .push(RUN::from(r#"["/bin/sh", "-c", "ps aux | grep app"]"#))
+            .push(RUN::from("ps aux | grep app"))
+            .push(CMD::from(r#"["echo", "$(date)"]"#))
+            .push(ENTRYPOINT::from(r#"["app", "--home=${HOME}"]"#))
+            .push(ENTRYPOINT::from(r#"["app", "--port", "80"]"#));
+        assert_eq!(
+            dockerfile.lint_with(&config),
+            vec![
+                LintWarning::ShellSyntaxInExecForm {
+                    index: 0,
+                    word: "|".to_string(),
+                },
+                LintWarning::ShellSyntaxInExecForm {
+                    index: 3,
+                    word: "$(date)".to_string(),
+                },
+                LintWarning::ShellSyntaxInExecForm {
+                    index: 4,
+                    word: "--home=${HOME}".to_string(),
+                },
+            ],
+        );
+        assert!(dockerfile.lint().is_empty());
+    }
+
+    fn overridden_instructions_config() -> LintConfig {
+        LintConfig {
+            overridden_instructions: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn overridden_instructions() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine AS base"))
+            .push(CMD::from("echo first"))
+            .push(ENTRYPOINT::from(r#"["app"]"#))
+            .push(CMD::from("echo second"))
+            .push(FROM::from("base"))
+            .push(CMD::from("echo third"))
+            .push(HEALTHCHECK::from("NONE"))
+            .push(HEALTHCHECK::from("CMD true"));
+        assert_eq!(
+            dockerfile.lint_with(&overridden_instructions_config()),
+            vec![
+                LintWarning::OverriddenInstruction {
+                    index: 1,
+                    keyword: "CMD".to_string(),
+                },
+                LintWarning::OverriddenInstruction {
+                    index: 6,
+                    keyword: "HEALTHCHECK".to_string(),
+                },
+            ],
+        );
+        assert_eq!(
+            dockerfile.lint_with(&overridden_instructions_config())[0].to_string(),
+            "instruction 1: CMD is overridden by a later CMD in the same stage"
+        );
+        assert!(dockerfile.lint().is_empty());
+    }
+
+    #[test]
+    fn overridden_entrypoints() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .push(ENTRYPOINT::from(r#"["first"]"#))
+            .push(ENTRYPOINT::from(r#"["second"]"#))
+            .push(CMD::from(r#"["--flag"]"#))
+            .push(ENTRYPOINT::from(r#"["third"]"#));
+        let overridden = dockerfile
+            .lint_with(&overridden_instructions_config())
+            .into_iter()
+            .map(|warning| match warning {
+                LintWarning::OverriddenInstruction { index, keyword } => (index, keyword),
+                warning => panic!("unexpected warning {}", warning),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            overridden,
+            vec![(1, "ENTRYPOINT".to_string()), (2, "ENTRYPOINT".to_string())],
+        );
+    }
+
+    #[test]
+    fn missing_assume_yes() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("debian"))
+            .push(RUN::from("apt-get update && apt-get install foo"))
+            .push(RUN::from(
+                "apt-get update && apt-get install -qy foo; yum install bar",
+            ))
+            .push(RUN::from(
+                "sudo dnf install --assumeyes baz && apk add curl",
+            ))
+            .push(RUN::from(r#"["apk", "add", "--no-cache", "git"]"#));
+        let config = LintConfig {
+            missing_assume_yes: true,
+            ..Default::default()
+        };
+        assert!(dockerfile.lint().is_empty());
+        assert_eq!(
+            dockerfile.lint_with(&config),
+            vec![
+                LintWarning::MissingAssumeYes {
+                    index: 1,
+                    command: "apt-get install".to_string(),
+                },
+                LintWarning::MissingAssumeYes {
+                    index: 2,
+                    command: "yum install".to_string(),
+                },
+                LintWarning::MissingAssumeYes {
+                    index: 3,
+                    command: "apk add".to_string(),
+                },
+            ],
+        );
+        assert_eq!(
+            dockerfile.lint_with(&config)[0].to_string(),
+            "instruction 1: apt-get install needs a non-interactive flag to not wait for confirmation"
+        );
+    }
+
+    #[test]
+    fn expose_without_command() {
+        let config = LintConfig {
+            expose_without_command: true,
+            ..Default::default()
+        };
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("nginx AS base"))
+            .push(EXPOSE::from("80"))
+            .push(FROM::from("node"))
+            .push(EXPOSE::from("3000"))
+            .push(CMD::from(r#"["node", "server.js"]"#));
+        assert_eq!(
+            dockerfile.lint_with(&config),
+            vec![LintWarning::ExposeWithoutCommand { index: 1 }],
+        );
+        assert_eq!(
+            dockerfile.lint_with(&config)[0].to_string(),
+            "instruction 1: EXPOSE in a stage without CMD or ENTRYPOINT"
+        );
+        assert!(dockerfile.lint().is_empty());
+    }
+
+    #[test]
+    fn whole_context_copy() {
+        let config = LintConfig {
+            whole_context_copy: true,
+            ..Default::default()
+        };
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("rust AS builder"))
+            .push(COPY::from(". ."))
+            .push(COPY::from("--chown=app ./ /app"))
+            .push(COPY::from("src /app/src"))
+            .push(FROM::from("debian"))
+            .push(COPY::from("--from=builder . /app"))
+            .push(ADD::from(". /app"));
+        assert_eq!(
+            dockerfile.lint_with(&config),
+            vec![
+                LintWarning::WholeContextCopy { index: 1 },
+                LintWarning::WholeContextCopy { index: 2 },
+                LintWarning::WholeContextCopy { index: 6 },
+            ],
+        );
+        assert_eq!(
+            dockerfile.lint_with(&config)[0].to_string(),
+            "instruction 1: copies the whole build context, exclude unneeded files with a .dockerignore"
+        );
+        assert!(dockerfile.lint().is_empty());
+    }
+
+    #[test]
+    fn non_deterministic_run() {
+        let config = LintConfig {
+            non_deterministic_run: Some(
+                NON_DETERMINISTIC_RUN_PATTERNS
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("debian"))
+            .push(RUN::from(
+                "apt-get update && curl -fsSL https://sh.rustup.rs | sh -s -- -y",
+            ))
+            .push(RUN::from("curl -fsSLO https://example.com/app-1.0.tar.gz"))
+            .push(RUN::from("cd /src; git clone https://github.com/org/repo"));
+        assert_eq!(
+            dockerfile.lint_with(&config),
+            vec![
+                LintWarning::NonDeterministicRun {
+                    index: 1,
+                    command: "curl -fsSL https://sh.rustup.rs | sh -s -- -y".to_string(),
+                },
+                LintWarning::NonDeterministicRun {
+                    index: 3,
+                    command: "git clone https://github.com/org/repo".to_string(),
+                },
+            ],
+        );
+        assert_eq!(
+            dockerfile.lint_with(&config)[0].to_string(),
+            "instruction 1: curl -fsSL https://sh.rustup.rs | sh -s -- -y may not be reproducible, pin a version or ref"
+        );
+        assert!(dockerfile.lint().is_empty());
+    }
+}
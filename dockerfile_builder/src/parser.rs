@@ -0,0 +1,440 @@
+//! Parser for existing Dockerfiles
+//!
+//! See [`Dockerfile::from_str`]
+
+use std::convert::Infallible;
+use std::str::FromStr;
+
+use crate::{
+    instruction::{
+        Instruction, ADD, ARG, CMD, COPY, ENTRYPOINT, ENV, EXPOSE, FROM, HEALTHCHECK, LABEL,
+        MAINTAINER, ONBUILD, RUN, SHELL, STOPSIGNAL, USER, VOLUME, WORKDIR,
+    },
+    Dockerfile,
+};
+
+impl FromStr for Dockerfile {
+    type Err = Infallible;
+
+    /// Parses a Dockerfile
+    ///
+    /// * The `# syntax=` and `# escape=` parser directives on the first lines set
+    ///   [`Dockerfile::syntax`] and [`Dockerfile::escape_char`]. Other directives there, e.g.
+    ///   `# check=`, are kept as [`Instruction::ANY`].
+    /// * Lines ending with the escape character, `\` by default, are merged with the following
+    ///   lines into one instruction. Like Docker, only the escape character is removed, the
+    ///   whitespace around it and the indentation of the next line are kept.
+    /// * Heredocs of `RUN`, `COPY` and `ADD` are kept in the instruction value.
+    /// * Comments starting with `# `, or a lone `#`, are parsed as [`Instruction::COMMENT`].
+    /// * Other comments, empty lines and unknown instructions are kept as [`Instruction::ANY`].
+    ///
+    /// Parsing the output of [`Dockerfile::to_string`] returns an equal Dockerfile, except that:
+    /// * the `comment` of instructions and multi-line [`Instruction::COMMENT`]s are parsed as one
+    ///   `COMMENT` per line, without trailing whitespace, and
+    /// * instructions continued on several lines, e.g. `RUN` commands separated by ` && \`,
+    ///   are merged into a single line, keeping the indentation of the continued lines.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::{Instruction, FROM, RUN};
+    /// let dockerfile: Dockerfile = "FROM alpine\nRUN apk update && \\\napk add curl"
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     dockerfile.into_inner(),
+    ///     vec![
+    ///         Instruction::from(FROM::from("alpine")),
+    ///         Instruction::from(RUN::from("apk update && apk add curl")),
+    ///     ],
+    /// );
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut dockerfile = Dockerfile::default();
+        let mut lines = s.lines().peekable();
+        while let Some(line) = lines.peek().copied() {
+            let Some((key, value)) = directive(line) else {
+                break;
+            };
+            match (key.as_str(), value.chars().collect::<Vec<_>>().as_slice()) {
+                ("syntax", _) => dockerfile.syntax = Some(value),
+                ("escape", &[escape @ ('\\' | '`')]) => dockerfile.escape = Some(escape),
+                ("escape", _) => break,
+                _ => dockerfile = dockerfile.push_any(line),
+            }
+            lines.next();
+        }
+        let escape = dockerfile.escape.unwrap_or('\\');
+        while let Some(line) = lines.next() {
+            if let Some(comment) = line.strip_prefix("# ").or((line == "#").then_some("")) {
+                dockerfile = dockerfile.comment(comment);
+                continue;
+            }
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                dockerfile = dockerfile.push_any(line);
+                continue;
+            }
+            let mut logical = line.to_string();
+            // Like BuildKit, only the escape and the whitespace after it are removed, the
+            // next line is appended as is
+            while let Some(head) = logical.trim_end_matches([' ', '\t']).strip_suffix(escape) {
+                let head = head.to_string();
+                // Comments and empty lines inside a continuation are skipped
+                let next = lines.find(|l| !l.trim().is_empty() && !l.trim_start().starts_with('#'));
+                match next {
+                    Some(next) => logical = format!("{}{}", head, next),
+                    None => logical = head,
+                }
+            }
+            let (keyword, value) = match logical.trim_start().split_once(char::is_whitespace) {
+                Some((keyword, value)) => (keyword.to_uppercase(), value.trim_start().to_string()),
+                None => {
+                    dockerfile = dockerfile.push_any(logical);
+                    continue;
+                }
+            };
+            let mut value = value;
+            if matches!(keyword.as_str(), "RUN" | "COPY" | "ADD") {
+                for delimiter in heredoc_delimiters(&value) {
+                    for body in lines.by_ref() {
+                        value.push('\n');
+                        value.push_str(body);
+                        if body.trim() == delimiter {
+                            break;
+                        }
+                    }
+                }
+            }
+            dockerfile = dockerfile.push(instruction(&keyword, value, &logical));
+        }
+        Ok(dockerfile)
+    }
+}
+
+impl Dockerfile {
+    /// Parses every [`Instruction::ANY`] holding a known instruction into its typed variant
+    ///
+    /// Raw strings are parsed like a Dockerfile line, see [`Dockerfile::from_str`]. Comments,
+    /// empty lines and unknown instructions are kept as `ANY`.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::{Instruction, RUN};
+    /// let dockerfile = Dockerfile::default()
+    ///     .push_any("RUN echo hi")
+    ///     .push_any("# not an instruction")
+    ///     .reclassify_any();
+    /// assert_eq!(
+    ///     dockerfile.into_inner(),
+    ///     vec![
+    ///         Instruction::from(RUN::from("echo hi")),
+    ///         Instruction::from("# not an instruction"),
+    ///     ],
+    /// );
+    /// ```
+    pub fn reclassify_any(mut self) -> Self {
+        let escape = self.escape;
+        for instruction in &mut self.instructions {
+            let Instruction::ANY(raw) = instruction else {
+                continue;
+            };
+            let source = match escape {
+                Some(escape) => format!("# escape={}\n{}", escape, raw),
+                None => raw.clone(),
+            };
+            let Ok(parsed) = source.parse::<Dockerfile>();
+            if let [typed] = parsed.instructions.as_slice() {
+                if !matches!(typed, Instruction::ANY(_) | Instruction::COMMENT(_)) {
+                    *instruction = typed.clone();
+                }
+            }
+        }
+        self
+    }
+}
+
+/// Returns the lowercase key and the value of a `# key=value` parser directive
+pub(crate) fn directive(line: &str) -> Option<(String, String)> {
+    let (key, value) = line.trim().strip_prefix('#')?.split_once('=')?;
+    let key = key.trim();
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some((key.to_lowercase(), value.trim().to_string()))
+}
+
+/// Maps an instruction keyword to its [`Instruction`] variant
+fn instruction(keyword: &str, value: String, line: &str) -> Instruction {
+    match keyword {
+        "FROM" => Instruction::FROM(FROM::from(value)),
+        "ENV" => Instruction::ENV(ENV::from(value)),
+        "RUN" => Instruction::RUN(RUN::from(value)),
+        "CMD" => Instruction::CMD(CMD::from(value)),
+        "LABEL" => Instruction::LABEL(LABEL::from(value)),
+        "EXPOSE" => Instruction::EXPOSE(EXPOSE::from(value)),
+        "ADD" => Instruction::ADD(ADD::from(value)),
+        "COPY" => Instruction::COPY(COPY::from(value)),
+        "ENTRYPOINT" => Instruction::ENTRYPOINT(ENTRYPOINT::from(value)),
+        "VOLUME" => Instruction::VOLUME(VOLUME::from(value)),
+        "USER" => Instruction::USER(USER::from(value)),
+        "WORKDIR" => Instruction::WORKDIR(WORKDIR::from(value)),
+        "ARG" => Instruction::ARG(ARG::from(value)),
+        "ONBUILD" => Instruction::ONBUILD(ONBUILD::from(value)),
+        "STOPSIGNAL" => Instruction::STOPSIGNAL(STOPSIGNAL::from(value)),
+        "HEALTHCHECK" => Instruction::HEALTHCHECK(HEALTHCHECK::from(value)),
+        "SHELL" => Instruction::SHELL(SHELL::from(value)),
+        "MAINTAINER" => Instruction::MAINTAINER(MAINTAINER::from(value)),
+        _ => Instruction::ANY(line.to_string()),
+    }
+}
+
+/// Returns the delimiters of the heredocs started in an instruction value, in order
+///
+/// Supports `<<EOF`, `<<-EOF`, `<<'EOF'` and `<<"EOF"`, the delimiter must be an identifier.
+/// Shifts in `$((...))` and `<<<` herestrings don't start a heredoc.
+fn heredoc_delimiters(value: &str) -> Vec<String> {
+    let mut delimiters = vec![];
+    let mut rest = value;
+    while let Some(start) = rest.find(['<', '$']) {
+        rest = &rest[start..];
+        if let Some(arithmetic) = rest.strip_prefix("$((") {
+            rest = skip_arithmetic(arithmetic);
+            continue;
+        }
+        if rest.starts_with("<<<") {
+            rest = rest.trim_start_matches('<');
+            continue;
+        }
+        let Some(word) = rest.strip_prefix("<<") else {
+            rest = &rest[1..];
+            continue;
+        };
+        rest = word;
+        let word = word.strip_prefix('-').unwrap_or(word);
+        let quote = word.chars().next().filter(|c| matches!(c, '\'' | '"'));
+        let word = &word[quote.map_or(0, char::len_utf8)..];
+        if !word.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            continue;
+        }
+        let delimiter = word
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .collect::<String>();
+        let closed = match quote {
+            Some(quote) => word[delimiter.len()..].starts_with(quote),
+            None => true,
+        };
+        if closed {
+            delimiters.push(delimiter);
+        }
+    }
+    delimiters
+}
+
+/// Returns the rest of `value` after the `))` closing an arithmetic expansion started by `$((`
+fn skip_arithmetic(value: &str) -> &str {
+    let mut depth = 2;
+    for (i, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return &value[i + 1..];
+        }
+    }
+    ""
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction_builder::{RunBuilder, RunHeredocBuilder};
+
+    fn parse(s: &str) -> Vec<Instruction> {
+        s.parse::<Dockerfile>().unwrap().into_inner()
+    }
+
+    #[test]
+    fn keywords() {
+        assert_eq!(
+            parse("from alpine AS base\n\n# install\nRUN  apk add curl\nMAINTAINER me\nUSER"),
+            vec![
+                Instruction::from(FROM::from("alpine AS base")),
+                Instruction::from(""),
+                Instruction::COMMENT("install".to_string()),
+                Instruction::from(RUN::from("apk add curl")),
+                Instruction::from(MAINTAINER::from("me")),
+                Instruction::from("USER"),
+            ],
+        );
+    }
+
+    #[test]
+    fn reclassify_any() {
+        let dockerfile = Dockerfile::default()
+            .push_any("FROM alpine")
+            .push_any("RUN x")
+            .push_any("run apk add \\\n    curl")
+            .push_any("# syntax=docker/dockerfile:1")
+            .push_any("")
+            .push_any("FOO bar")
+            .push_any("RUN a\nRUN b")
+            .reclassify_any();
+        assert_eq!(
+            dockerfile.into_inner(),
+            vec![
+                Instruction::from(FROM::from("alpine")),
+                Instruction::from(RUN::from("x")),
+                Instruction::from(RUN::from("apk add     curl")),
+                Instruction::from("# syntax=docker/dockerfile:1"),
+                Instruction::from(""),
+                Instruction::from("FOO bar"),
+                Instruction::from("RUN a\nRUN b"),
+            ],
+        );
+
+        let dockerfile = Dockerfile::default()
+            .escape_char('`')
+            .unwrap()
+            .push_any("RUN dir `\n    c:\\")
+            .reclassify_any();
+        assert_eq!(
+            dockerfile.into_inner(),
+            vec![Instruction::from(RUN::from("dir     c:\\"))],
+        );
+    }
+
+    #[test]
+    fn continuations() {
+        let dockerfile = "RUN apt-get update \\\n    # comment\n\n    && apt-get install -y \\\n      curl\nEXPOSE 80";
+        assert_eq!(
+            parse(dockerfile),
+            vec![
+                Instruction::from(RUN::from(
+                    "apt-get update     && apt-get install -y       curl"
+                )),
+                Instruction::from(EXPOSE::from("80")),
+            ],
+        );
+
+        assert_eq!(
+            parse("RUN echo foo\\\nbar \\  \n&& echo baz"),
+            vec![Instruction::from(RUN::from("echo foobar && echo baz"))],
+        );
+    }
+
+    #[test]
+    fn heredocs() {
+        let dockerfile = "RUN <<EOF\necho \\\nEOF\nCOPY <<-'A' <<\"B\" /app/\na\nA\nb\nB\nUSER app";
+        assert_eq!(
+            parse(dockerfile),
+            vec![
+                Instruction::from(RUN::from("<<EOF\necho \\\nEOF")),
+                Instruction::from(COPY::from("<<-'A' <<\"B\" /app/\na\nA\nb\nB")),
+                Instruction::from(USER::from("app")),
+            ],
+        );
+    }
+
+    #[test]
+    fn heredoc_like_operators() {
+        assert_eq!(
+            parse("FROM alpine\nRUN echo $((1<<2))\nUSER nobody\nCMD [\"sh\"]"),
+            vec![
+                Instruction::from(FROM::from("alpine")),
+                Instruction::from(RUN::from("echo $((1<<2))")),
+                Instruction::from(USER::from("nobody")),
+                Instruction::from(CMD::from(r#"["sh"]"#)),
+            ],
+        );
+        assert_eq!(
+            parse("RUN cat <<<word\nUSER nobody\nRUN x=$(( (1 << 2) )) <<EOF\nbody\nEOF"),
+            vec![
+                Instruction::from(RUN::from("cat <<<word")),
+                Instruction::from(USER::from("nobody")),
+                Instruction::from(RUN::from("x=$(( (1 << 2) )) <<EOF\nbody\nEOF")),
+            ],
+        );
+        assert_eq!(
+            heredoc_delimiters("<<'EOF <<\"A\" <<-_b1 <<2"),
+            vec!["A", "_b1"]
+        );
+    }
+
+    #[test]
+    fn escape_directive() {
+        let dockerfile = "# escape=`\nFROM windows\nRUN dir c:\\ `\n  && echo done"
+            .parse::<Dockerfile>()
+            .unwrap();
+        let expected = Dockerfile::default()
+            .push(FROM::from("windows"))
+            .push(RUN::from("dir c:\\   && echo done"))
+            .escape_char('`')
+            .unwrap();
+        assert_eq!(dockerfile, expected);
+
+        assert_eq!(
+            parse("FROM windows\n# escape=`"),
+            vec![
+                Instruction::from(FROM::from("windows")),
+                Instruction::COMMENT("escape=`".to_string()),
+            ],
+        );
+    }
+
+    #[test]
+    fn other_directives() {
+        assert_eq!(
+            parse(
+                "# syntax=docker/dockerfile:1\n# check=error=true\nFROM alpine\n# check=skip=all"
+            ),
+            vec![
+                Instruction::from("# check=error=true"),
+                Instruction::from(FROM::from("alpine")),
+                Instruction::COMMENT("check=skip=all".to_string()),
+            ],
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let dockerfile = Dockerfile::default()
+            .syntax("docker/dockerfile:1")
+            .push(FROM::from("rust:1.70 AS builder"))
+            .push(ENV::from(r#"GREETING="hello world""#))
+            .push(
+                RunBuilder::builder()
+                    .command("cargo build")
+                    .write_file("/app/config", "a = 1")
+                    .build()
+                    .unwrap(),
+            )
+            .push(RunHeredocBuilder::builder().line("make").build().unwrap())
+            .push(CMD::from(r#"["/app/bin"]"#))
+            .comment("done")
+            .escape_char('`')
+            .unwrap();
+        let parsed = dockerfile.to_string().parse::<Dockerfile>().unwrap();
+        assert_eq!(parsed, dockerfile);
+        assert_eq!(parsed.to_string(), dockerfile.to_string());
+    }
+
+    #[test]
+    fn multi_line_comment_round_trip() {
+        let dockerfile = Dockerfile::default()
+            .comment("first \nsecond")
+            .push(FROM::from("alpine"));
+        let parsed = dockerfile.to_string().parse::<Dockerfile>().unwrap();
+        assert_ne!(parsed, dockerfile);
+        assert_eq!(
+            parsed.instructions,
+            vec![
+                Instruction::COMMENT("first".to_string()),
+                Instruction::COMMENT("second".to_string()),
+                Instruction::from(FROM::from("alpine")),
+            ]
+        );
+        assert_eq!(parsed.to_string(), "# first\n# second\nFROM alpine");
+    }
+}
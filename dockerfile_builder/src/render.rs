@@ -0,0 +1,181 @@
+//! Rendering options for [`Dockerfile`]
+//!
+//! [`Dockerfile::to_string`](ToString::to_string) renders with the default [`RenderOptions`],
+//! use [`Dockerfile::render_with`] to change them.
+//!
+//! ```
+//! use dockerfile_builder::Dockerfile;
+//! use dockerfile_builder::instruction::ENTRYPOINT;
+//! use dockerfile_builder::render::RenderOptions;
+//!
+//! let dockerfile = Dockerfile::default()
+//!     .push(ENTRYPOINT::from(r#"["app", "--port", "80"]"#));
+//!
+//! let options = RenderOptions {
+//!     exec_array_wrap: Some(2),
+//!     ..Default::default()
+//! };
+//! assert_eq!(
+//!     dockerfile.render_with(&options),
+//!     "ENTRYPOINT [ \\\n    \"app\", \\\n    \"--port\", \\\n    \"80\" \\\n]",
+//! );
+//! ```
+//!
+//! `Display` doesn't end the Dockerfile with `\n`, for backward compatibility. Set
+//! [`RenderOptions::trailing_newline`] to render it like
+//! [`Dockerfile::write_to_file`](crate::Dockerfile::write_to_file), as expected by most tools:
+//!
+//! ```
+//! # use dockerfile_builder::Dockerfile;
+//! # use dockerfile_builder::instruction::FROM;
+//! # use dockerfile_builder::render::RenderOptions;
+//! let dockerfile = Dockerfile::default().push(FROM::from("alpine"));
+//! assert_eq!(dockerfile.to_string(), "FROM alpine");
+//!
+//! let options = RenderOptions {
+//!     trailing_newline: true,
+//!     ..Default::default()
+//! };
+//! assert_eq!(dockerfile.render_with(&options), "FROM alpine\n");
+//! ```
+
+use crate::{
+    analysis::exec_form, instruction::Instruction, instruction_builder::json_string, Dockerfile,
+};
+
+/// Options for [`Dockerfile::render_with`]
+///
+/// The default options render every instruction on a single line, as is.
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// Render exec form arrays with more elements than this one element per line, using line
+    /// continuations. Applies to instructions whose value is a JSON array, e.g. `CMD` or
+    /// `ENTRYPOINT`.
+    pub exec_array_wrap: Option<usize>,
+    /// End the last line with `\n`, like [`Dockerfile::to_writer`]. Nothing is rendered for a
+    /// Dockerfile without instructions or parser directives.
+    pub trailing_newline: bool,
+}
+
+impl Dockerfile {
+    /// Renders the Dockerfile with `options`
+    pub fn render_with(&self, options: &RenderOptions) -> String {
+        let lines = self.lines(options).collect::<Vec<_>>();
+        let mut rendered = lines.join("\n");
+        if options.trailing_newline && !lines.is_empty() {
+            rendered.push('\n');
+        }
+        rendered
+    }
+}
+
+/// Applies `options` to an instruction before it's rendered
+pub(crate) fn render_instruction(instruction: &Instruction, options: &RenderOptions) -> String {
+    let wrap = match (instruction, options.exec_array_wrap) {
+        (Instruction::ANY(_) | Instruction::COMMENT(_), _) | (_, None) => None,
+        (_, Some(max)) => exec_form(instruction.value()).filter(|words| words.len() > max),
+    };
+    let Some(words) = wrap else {
+        return instruction.to_string();
+    };
+    let elements = words
+        .iter()
+        .map(|word| json_string(word))
+        .collect::<Vec<_>>()
+        .join(", \\\n    ");
+    let mut wrapped = instruction.clone();
+    *wrapped.value_mut() = format!("[ \\\n    {} \\\n]", elements);
+    wrapped.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction::{CMD, ENTRYPOINT, FROM, RUN};
+    use expect_test::expect;
+
+    #[test]
+    fn exec_array_wrap() {
+        let dockerfile = Dockerfile::default()
+            .push(ENTRYPOINT::from(
+                r#"["app", "-a", "-b", "-c", "say \"hi\""]"#,
+            ))
+            .push(CMD::from(r#"["-l"]"#))
+            .push(RUN::from("echo a b c d e"));
+
+        let options = RenderOptions {
+            exec_array_wrap: Some(3),
+            ..Default::default()
+        };
+        let expected = expect![[r#"
+            ENTRYPOINT [ \
+                "app", \
+                "-a", \
+                "-b", \
+                "-c", \
+                "say \"hi\"" \
+            ]
+            CMD ["-l"]
+            RUN echo a b c d e"#]];
+        expected.assert_eq(&dockerfile.render_with(&options));
+
+        let options = RenderOptions {
+            exec_array_wrap: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(dockerfile.render_with(&options), dockerfile.to_string());
+        assert_eq!(
+            dockerfile.render_with(&RenderOptions::default()),
+            dockerfile.to_string()
+        );
+    }
+
+    #[test]
+    fn exec_array_wrap_escape_char() {
+        let dockerfile = Dockerfile::default()
+            .push(CMD::from(r#"["a", "b"]"#))
+            .escape_char('`')
+            .unwrap();
+        let options = RenderOptions {
+            exec_array_wrap: Some(1),
+            ..Default::default()
+        };
+        let expected = expect![[r#"
+            # escape=`
+            CMD [ `
+                "a", `
+                "b" `
+            ]"#]];
+        expected.assert_eq(&dockerfile.render_with(&options));
+    }
+
+    #[test]
+    fn trailing_newline() {
+        let options = RenderOptions {
+            trailing_newline: true,
+            ..Default::default()
+        };
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .push(RUN::from("echo $HOME"));
+        let mut written = Vec::new();
+        dockerfile.to_writer(&mut written).unwrap();
+        assert_eq!(
+            dockerfile.render_with(&options).as_bytes(),
+            written.as_slice()
+        );
+        assert_eq!(Dockerfile::default().render_with(&options), "");
+
+        let dockerfile = Dockerfile::default().syntax("docker/dockerfile:1");
+        let mut written = Vec::new();
+        dockerfile.to_writer(&mut written).unwrap();
+        assert_eq!(
+            dockerfile.render_with(&options).as_bytes(),
+            written.as_slice()
+        );
+        assert_eq!(
+            dockerfile.render_with(&options),
+            "# syntax=docker/dockerfile:1\n"
+        );
+    }
+}
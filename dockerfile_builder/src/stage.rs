@@ -0,0 +1,295 @@
+//! Build stages of a multi-stage [`Dockerfile`]
+//!
+//! See [`Stage`]
+
+use crate::{
+    analysis::stage_ranges,
+    instruction::{Instruction, COPY, FROM},
+    instruction_builder::{BuildResult, BuilderError},
+    lint::{flag, parse_from},
+    Dockerfile,
+};
+
+/// Build stage, i.e. a `FROM <image> AS <name>` followed by its instructions
+///
+/// Stages are added to a Dockerfile with [`Dockerfile::add_stage`] or [`Dockerfile::stage`],
+/// which flatten them into normal instructions.
+///
+/// ```
+/// use dockerfile_builder::Dockerfile;
+/// use dockerfile_builder::instruction::{CMD, RUN};
+/// use dockerfile_builder::stage::Stage;
+///
+/// let builder = Stage::new("builder", "rust:1.70").push(RUN::from("cargo build --release"));
+/// let runtime = Stage::new("runtime", "debian")
+///     .copy_from(&builder, "/target/release/app", "/app")
+///     .push(CMD::from(r#"["/app"]"#));
+///
+/// let dockerfile = Dockerfile::default().add_stage(builder).add_stage(runtime);
+/// assert_eq!(
+///     dockerfile.to_string(),
+///     r#"FROM rust:1.70 AS builder
+/// RUN cargo build --release
+/// FROM debian AS runtime
+/// COPY --from=builder /target/release/app /app
+/// CMD ["/app"]"#,
+/// );
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Stage {
+    name: String,
+    image: String,
+    instructions: Vec<Instruction>,
+}
+
+impl Stage {
+    /// Creates an empty stage named `name`, built from `image`
+    pub fn new<N: Into<String>, I: Into<String>>(name: N, image: I) -> Self {
+        Stage {
+            name: name.into(),
+            image: image.into(),
+            instructions: vec![],
+        }
+    }
+
+    /// Returns the name of the stage
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the image the stage is built from
+    pub fn image(&self) -> &str {
+        &self.image
+    }
+
+    /// Adds an [`Instruction`] to the end of the stage
+    pub fn push<T: Into<Instruction>>(mut self, instruction: T) -> Self {
+        self.instructions.push(instruction.into());
+        self
+    }
+
+    /// Adds any raw string to the end of the stage
+    pub fn push_any<T: Into<String>>(mut self, instruction: T) -> Self {
+        self.instructions.push(Instruction::ANY(instruction.into()));
+        self
+    }
+
+    /// Adds a `COPY --from=<stage> <src> <dest>` copying files from another stage
+    pub fn copy_from<S: Into<String>, D: Into<String>>(
+        self,
+        stage: &Stage,
+        src: S,
+        dest: D,
+    ) -> Self {
+        self.push(COPY::from(format!(
+            "--from={} {} {}",
+            stage.name,
+            src.into(),
+            dest.into()
+        )))
+    }
+
+    /// Retrieves the instructions of the stage, starting with its `FROM`
+    pub fn into_inner(self) -> Vec<Instruction> {
+        let from = FROM::from(format!("{} AS {}", self.image, self.name));
+        std::iter::once(Instruction::from(from))
+            .chain(self.instructions)
+            .collect()
+    }
+}
+
+impl Dockerfile {
+    /// Adds the instructions of a [`Stage`] to the end of the Dockerfile, starting with its
+    /// `FROM <image> AS <name>`
+    pub fn add_stage(self, stage: Stage) -> Self {
+        self.append(stage.into_inner())
+    }
+
+    /// Adds a stage built by the given closure to the end of the Dockerfile
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::RUN;
+    /// let dockerfile = Dockerfile::default()
+    ///     .stage("builder", "rust", |stage| stage.push(RUN::from("cargo build")));
+    /// assert_eq!(dockerfile.to_string(), "FROM rust AS builder\nRUN cargo build");
+    /// ```
+    pub fn stage<N, I, F>(self, name: N, image: I, f: F) -> Self
+    where
+        N: Into<String>,
+        I: Into<String>,
+        F: FnOnce(Stage) -> Stage,
+    {
+        self.add_stage(f(Stage::new(name, image)))
+    }
+
+    /// Reorders the named build stages according to `order`
+    ///
+    /// Each stage is moved as a whole, from its `FROM` up to the next one, together with the
+    /// comments right before its `FROM`. The stages in `order` take the places of each other, so
+    /// instructions before the first `FROM` and stages not in `order` keep their position.
+    ///
+    /// Returns an error if a stage in `order` doesn't exist or is listed twice, or if a stage is
+    /// referenced by its index, e.g. `COPY --from=0`, as the index would change.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::{COPY, FROM, RUN};
+    /// let dockerfile = Dockerfile::default()
+    ///     .push(FROM::from("rust AS builder"))
+    ///     .push(COPY::from("--from=deps /deps /deps"))
+    ///     .push(FROM::from("rust AS deps"))
+    ///     .push(RUN::from("cargo fetch"))
+    ///     .reorder_stages(&["deps", "builder"])
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     dockerfile.to_string(),
+    ///     "FROM rust AS deps\nRUN cargo fetch\nFROM rust AS builder\nCOPY --from=deps /deps /deps",
+    /// );
+    /// ```
+    pub fn reorder_stages(mut self, order: &[&str]) -> BuildResult<Self> {
+        let instructions = &self.instructions;
+        let index_reference = instructions
+            .iter()
+            .filter(|i| matches!(i, Instruction::COPY(_) | Instruction::ADD(_)))
+            .find_map(|i| {
+                flag(i.value(), "from").filter(|from| from.chars().all(|c| c.is_ascii_digit()))
+            });
+        if let Some(from) = index_reference {
+            return Err(BuilderError::Invalid(format!(
+                "stages can't be reordered, --from={} references a stage by index",
+                from
+            )));
+        }
+
+        let mut ranges = stage_ranges(instructions);
+        // Comments right before a FROM document its stage
+        for i in 1..ranges.len() {
+            while ranges[i].start > ranges[i - 1].start + 1
+                && is_comment(&instructions[ranges[i].start - 1])
+            {
+                ranges[i - 1].end -= 1;
+                ranges[i].start -= 1;
+            }
+        }
+        let names = ranges
+            .iter()
+            .map(|range| {
+                instructions[range.clone()].iter().find_map(|i| match i {
+                    Instruction::FROM(from) => parse_from(&from.value).1,
+                    _ => None,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut moved = vec![];
+        for name in order {
+            let position = names
+                .iter()
+                .position(|n| n.is_some_and(|n| n.eq_ignore_ascii_case(name)))
+                .ok_or_else(|| BuilderError::Invalid(format!("stage {} not found", name)))?;
+            if moved.contains(&position) {
+                return Err(BuilderError::Invalid(format!(
+                    "stage {} is listed more than once",
+                    name
+                )));
+            }
+            moved.push(position);
+        }
+        let mut slots = moved.clone();
+        slots.sort_unstable();
+
+        let mut reordered = Vec::with_capacity(instructions.len());
+        for (position, range) in ranges.iter().enumerate() {
+            let range = match slots.iter().position(|&slot| slot == position) {
+                Some(slot) => &ranges[moved[slot]],
+                None => range,
+            };
+            reordered.extend_from_slice(&instructions[range.clone()]);
+        }
+        self.instructions = reordered;
+        Ok(self)
+    }
+}
+
+/// Returns `true` for comment lines, which aren't instructions
+fn is_comment(instruction: &Instruction) -> bool {
+    match instruction {
+        Instruction::COMMENT(_) => true,
+        Instruction::ANY(raw) => raw.trim_start().starts_with('#'),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction::{ARG, RUN};
+    use expect_test::expect;
+
+    #[test]
+    fn stages() {
+        let deps = Stage::new("deps", "node:18").push(RUN::from("npm ci"));
+        let dockerfile = Dockerfile::default()
+            .push(ARG::from("NODE_ENV=production"))
+            .stage("build", "node:18", |stage| {
+                stage
+                    .copy_from(&deps, "/node_modules", "./node_modules")
+                    .push_any("# build the app")
+                    .push(RUN::from("npm run build"))
+            });
+        let dockerfile = Dockerfile::default()
+            .append(dockerfile.into_inner())
+            .add_stage(deps);
+
+        let expected = expect![[r#"
+            ARG NODE_ENV=production
+            FROM node:18 AS build
+            COPY --from=deps /node_modules ./node_modules
+            # build the app
+            RUN npm run build
+            FROM node:18 AS deps
+            RUN npm ci"#]];
+        expected.assert_eq(&dockerfile.to_string());
+    }
+
+    #[test]
+    fn reorder_stages() {
+        let dockerfile = Dockerfile::default()
+            .syntax("docker/dockerfile:1")
+            .push(ARG::from("NODE_ENV=production"))
+            .stage("build", "node:18", |stage| {
+                stage
+                    .push_any("# build the app")
+                    .push(RUN::from("npm run build"))
+            })
+            .comment("install the dependencies")
+            .stage("deps", "node:18", |stage| stage.push(RUN::from("npm ci")))
+            .push(FROM::from("nginx"))
+            .reorder_stages(&["deps", "build"])
+            .unwrap();
+
+        let expected = expect![[r#"
+            # syntax=docker/dockerfile:1
+            ARG NODE_ENV=production
+            # install the dependencies
+            FROM node:18 AS deps
+            RUN npm ci
+            FROM node:18 AS build
+            # build the app
+            RUN npm run build
+            FROM nginx"#]];
+        expected.assert_eq(&dockerfile.to_string());
+
+        let err = dockerfile.clone().reorder_stages(&["test"]).unwrap_err();
+        let expected = expect!["stage test not found"];
+        expected.assert_eq(&err.to_string());
+
+        let err = dockerfile
+            .push(COPY::from("--from=0 /app /app"))
+            .reorder_stages(&["build", "deps"])
+            .unwrap_err();
+        let expected = expect!["stages can't be reordered, --from=0 references a stage by index"];
+        expected.assert_eq(&err.to_string());
+    }
+}
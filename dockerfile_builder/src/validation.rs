@@ -0,0 +1,466 @@
+//! Validation of a [`Dockerfile`]
+//!
+//! Unlike [lints](crate::lint), validation errors are rules a Dockerfile must follow, e.g. to
+//! enforce image hygiene in CI.
+//!
+//! ```
+//! use dockerfile_builder::Dockerfile;
+//! use dockerfile_builder::instruction::{FROM, RUN};
+//! use dockerfile_builder::validation::{ValidationConfig, ValidationError};
+//!
+//! let dockerfile = Dockerfile::default()
+//!     .push(FROM::from("alpine"))
+//!     .push(RUN::from("apk add curl"))
+//!     .push(RUN::from("apk add git"));
+//!
+//! let config = ValidationConfig {
+//!     max_layers: Some(1),
+//! };
+//! assert_eq!(
+//!     dockerfile.validate_with(&config),
+//!     Err(ValidationError::TooManyLayers { layers: 2, max: 1 }),
+//! );
+//! ```
+
+use std::fmt::{self, Display};
+
+use crate::{
+    instruction::Instruction,
+    lint::{flag, parse_from},
+    Dockerfile,
+};
+
+/// Errors returned by [`Dockerfile::validate`] and [`Dockerfile::validate_with`]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ValidationError {
+    /// The Dockerfile has no `FROM` instruction
+    MissingFrom,
+    /// An instruction other than `ARG` or a comment comes before the first `FROM`
+    InstructionBeforeFrom { index: usize },
+    /// `COPY --from=<name>` references a stage that is only declared after it. Names that aren't
+    /// stages of the Dockerfile are taken as images, e.g. `--from=nginx`. Numeric values, e.g.
+    /// `--from=0`, are stage indexes and must reference a previous stage.
+    UnknownStage { index: usize, name: String },
+    /// The final stage adds more layers than [`ValidationConfig::max_layers`], see
+    /// [`Dockerfile::estimated_layers`]
+    TooManyLayers { layers: usize, max: usize },
+    /// `WORKDIR` contains a `..` component, trailing whitespace, or a backslash in a stage that
+    /// isn't based on a Windows image
+    SuspiciousWorkdir { index: usize, path: String },
+    /// The Dockerfile has more than one build stage, see [`Dockerfile::require_single_stage`]
+    MultipleStages { stages: usize },
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::MissingFrom => write!(f, "the Dockerfile has no FROM instruction"),
+            ValidationError::InstructionBeforeFrom { index } => write!(
+                f,
+                "instruction {}: only ARG and comments may come before the first FROM",
+                index
+            ),
+            ValidationError::UnknownStage { index, name } => write!(
+                f,
+                "instruction {}: --from={} is not a previous build stage",
+                index, name
+            ),
+            ValidationError::TooManyLayers { layers, max } => write!(
+                f,
+                "the final stage adds {} layers, more than the maximum of {}",
+                layers, max
+            ),
+            ValidationError::SuspiciousWorkdir { index, path } => write!(
+                f,
+                "instruction {}: WORKDIR {:?} is likely a path mistake",
+                index, path
+            ),
+            ValidationError::MultipleStages { stages } => write!(
+                f,
+                "the Dockerfile has {} build stages, only a single stage is supported",
+                stages
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Configuration for [`Dockerfile::validate_with`]
+///
+/// All configurable rules are disabled by default.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationConfig {
+    /// Maximum number of layers added by the final stage
+    pub max_layers: Option<usize>,
+}
+
+impl Dockerfile {
+    /// Validates the structure of the Dockerfile
+    ///
+    /// * The first instruction, besides `ARG`s and comments, must be a `FROM`.
+    /// * `COPY --from=<name>` must reference a previous build stage.
+    /// * `WORKDIR` must not contain `..` components or trailing whitespace, nor backslashes unless
+    ///   the stage is based on a Windows image.
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::{ARG, FROM, RUN};
+    /// # use dockerfile_builder::validation::ValidationError;
+    /// let dockerfile = Dockerfile::default()
+    ///     .push(ARG::from("VERSION=3.18"))
+    ///     .push(FROM::from("alpine:${VERSION}"));
+    /// assert_eq!(dockerfile.validate(), Ok(()));
+    ///
+    /// let dockerfile = Dockerfile::default()
+    ///     .push(RUN::from("echo $HOME"))
+    ///     .push(FROM::from("alpine"));
+    /// assert_eq!(
+    ///     dockerfile.validate(),
+    ///     Err(ValidationError::InstructionBeforeFrom { index: 0 }),
+    /// );
+    /// ```
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.validate_with(&ValidationConfig::default())
+    }
+
+    /// Validates the Dockerfile like [`Dockerfile::validate`] and against the rules enabled in
+    /// `config`
+    ///
+    /// Returns the first rule that is broken.
+    pub fn validate_with(&self, config: &ValidationConfig) -> Result<(), ValidationError> {
+        let first = self.instructions.iter().enumerate().find(|(_, i)| match i {
+            Instruction::ARG(_) | Instruction::COMMENT(_) => false,
+            Instruction::ANY(raw) => !raw.trim().is_empty() && !raw.trim().starts_with('#'),
+            _ => true,
+        });
+        match first {
+            None => return Err(ValidationError::MissingFrom),
+            Some((_, Instruction::FROM(_))) => {}
+            Some((_, Instruction::ANY(raw))) if is_from(raw) => {}
+            Some((index, _)) => return Err(ValidationError::InstructionBeforeFrom { index }),
+        }
+        unknown_stages(&self.instructions)?;
+        suspicious_workdirs(&self.instructions)?;
+        if let Some(max) = config.max_layers {
+            let layers = self.estimated_layers();
+            if layers > max {
+                return Err(ValidationError::TooManyLayers { layers, max });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns an error unless the Dockerfile has exactly one `FROM` instruction
+    ///
+    /// ```
+    /// # use dockerfile_builder::Dockerfile;
+    /// # use dockerfile_builder::instruction::{FROM, RUN};
+    /// # use dockerfile_builder::validation::ValidationError;
+    /// let dockerfile = Dockerfile::default()
+    ///     .push(FROM::from("alpine"))
+    ///     .push(RUN::from("apk add curl"));
+    /// assert_eq!(dockerfile.require_single_stage(), Ok(()));
+    ///
+    /// let dockerfile = dockerfile.push(FROM::from("scratch"));
+    /// assert_eq!(
+    ///     dockerfile.require_single_stage(),
+    ///     Err(ValidationError::MultipleStages { stages: 2 }),
+    /// );
+    /// ```
+    pub fn require_single_stage(&self) -> Result<(), ValidationError> {
+        let stages = self
+            .instructions
+            .iter()
+            .filter(|i| match i {
+                Instruction::FROM(_) => true,
+                Instruction::ANY(raw) => is_from(raw),
+                _ => false,
+            })
+            .count();
+        match stages {
+            0 => Err(ValidationError::MissingFrom),
+            1 => Ok(()),
+            stages => Err(ValidationError::MultipleStages { stages }),
+        }
+    }
+}
+
+fn unknown_stages(instructions: &[Instruction]) -> Result<(), ValidationError> {
+    let all_stages = instructions
+        .iter()
+        .filter_map(|instruction| match instruction {
+            Instruction::FROM(from) => parse_from(&from.value).1.map(|n| n.to_lowercase()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let mut stages = vec![];
+    let mut stage_count = 0;
+    for (index, instruction) in instructions.iter().enumerate() {
+        match instruction {
+            Instruction::FROM(from) => {
+                let (_, name) = parse_from(&from.value);
+                stages.extend(name.map(|n| n.to_lowercase()));
+                stage_count += 1;
+            }
+            Instruction::COPY(copy) => {
+                let Some(name) = flag(&copy.value, "from") else {
+                    continue;
+                };
+                let is_known = match name.parse::<usize>() {
+                    Ok(stage_index) => stage_index + 1 < stage_count,
+                    // Names of stages declared later are mistakes, other names are images
+                    Err(_) => {
+                        let name = name.to_lowercase();
+                        stages.contains(&name) || !all_stages.contains(&name)
+                    }
+                };
+                if !is_known {
+                    return Err(ValidationError::UnknownStage {
+                        index,
+                        name: name.to_string(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn suspicious_workdirs(instructions: &[Instruction]) -> Result<(), ValidationError> {
+    let mut windows_stages = vec![];
+    let mut windows = false;
+    for (index, instruction) in instructions.iter().enumerate() {
+        match instruction {
+            Instruction::FROM(from) => {
+                let (image, name) = parse_from(&from.value);
+                let image = image.to_lowercase();
+                windows = flag(&from.value, "platform").is_some_and(|p| p.starts_with("windows"))
+                    || ["windows", "servercore", "nanoserver"]
+                        .iter()
+                        .any(|w| image.contains(w))
+                    || windows_stages.contains(&image);
+                if let (true, Some(name)) = (windows, name) {
+                    windows_stages.push(name.to_lowercase());
+                }
+            }
+            Instruction::WORKDIR(workdir) => {
+                let path = &workdir.value;
+                let traversal = path.split(['/', '\\']).any(|part| part.trim() == "..");
+                let backslash = !windows && path.contains('\\');
+                if traversal || backslash || path.ends_with(char::is_whitespace) {
+                    return Err(ValidationError::SuspiciousWorkdir {
+                        index,
+                        path: path.to_string(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Returns `true` if a raw instruction is a `FROM`
+fn is_from(raw: &str) -> bool {
+    raw.split_whitespace()
+        .next()
+        .is_some_and(|keyword| keyword.eq_ignore_ascii_case("FROM"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction::{ARG, COPY, FROM, RUN, WORKDIR};
+    use crate::instruction_builder::CopyBuilder;
+
+    #[test]
+    fn max_layers() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .push(COPY::from(". /app"))
+            .push(RUN::from("make"));
+
+        let config = ValidationConfig {
+            max_layers: Some(2),
+        };
+        assert_eq!(dockerfile.validate_with(&config), Ok(()));
+
+        let config = ValidationConfig {
+            max_layers: Some(1),
+        };
+        let err = dockerfile.validate_with(&config).unwrap_err();
+        assert_eq!(err, ValidationError::TooManyLayers { layers: 2, max: 1 });
+        assert_eq!(
+            err.to_string(),
+            "the final stage adds 2 layers, more than the maximum of 1"
+        );
+
+        assert_eq!(
+            dockerfile.validate_with(&ValidationConfig::default()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn from_first() {
+        let dockerfile = Dockerfile::default()
+            .comment("build")
+            .push(ARG::from("BASE"))
+            .push_any("")
+            .push(FROM::from("${BASE}"))
+            .push(RUN::from("make"));
+        assert_eq!(dockerfile.validate(), Ok(()));
+
+        let dockerfile = Dockerfile::default()
+            .push(ARG::from("BASE"))
+            .push_any("from alpine");
+        assert_eq!(dockerfile.validate(), Ok(()));
+
+        let dockerfile = Dockerfile::default()
+            .push(ARG::from("BASE"))
+            .push(COPY::from(". /app"))
+            .push(FROM::from("alpine"));
+        let err = dockerfile.validate().unwrap_err();
+        assert_eq!(err, ValidationError::InstructionBeforeFrom { index: 1 });
+        assert_eq!(
+            err.to_string(),
+            "instruction 1: only ARG and comments may come before the first FROM"
+        );
+
+        let dockerfile = Dockerfile::default().push(ARG::from("BASE"));
+        assert_eq!(dockerfile.validate(), Err(ValidationError::MissingFrom));
+        assert_eq!(
+            Dockerfile::default().validate(),
+            Err(ValidationError::MissingFrom)
+        );
+    }
+
+    #[test]
+    fn unknown_stages() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("rust AS Builder"))
+            .push(FROM::from("alpine"))
+            .push(COPY::from("--from=builder /app /app"))
+            .push(COPY::from("--from=nginx:latest /etc/nginx /etc/nginx"))
+            .push(COPY::from("--from=ghcr.io/org/tool /bin/tool /bin/tool"));
+        assert_eq!(dockerfile.validate(), Ok(()));
+
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .push(COPY::from("--from=builder /app /app"))
+            .push(FROM::from("rust AS builder"));
+        let err = dockerfile.validate().unwrap_err();
+        assert_eq!(
+            err,
+            ValidationError::UnknownStage {
+                index: 1,
+                name: "builder".to_string(),
+            },
+        );
+        assert_eq!(
+            err.to_string(),
+            "instruction 1: --from=builder is not a previous build stage"
+        );
+    }
+
+    #[test]
+    fn bare_image_names() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .push(COPY::from("--from=nginx /etc/nginx /etc/nginx"))
+            .push(COPY::from("--from=busybox /bin/busybox /bin/busybox"));
+        assert_eq!(dockerfile.validate(), Ok(()));
+    }
+
+    #[test]
+    fn numeric_stages() {
+        let copy = CopyBuilder::builder()
+            .from("0")
+            .src("/app")
+            .dest("/app")
+            .build()
+            .unwrap();
+        assert_eq!(copy.to_string(), "COPY --from=0 /app /app");
+
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("rust"))
+            .push(FROM::from("alpine"))
+            .push(copy);
+        assert_eq!(dockerfile.validate(), Ok(()));
+
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .push(COPY::from("--from=0 /app /app"));
+        assert_eq!(
+            dockerfile.validate(),
+            Err(ValidationError::UnknownStage {
+                index: 1,
+                name: "0".to_string(),
+            }),
+        );
+    }
+
+    #[test]
+    fn suspicious_workdirs() {
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .push(WORKDIR::from("/app/../etc"));
+        let err = dockerfile.validate().unwrap_err();
+        assert_eq!(
+            err,
+            ValidationError::SuspiciousWorkdir {
+                index: 1,
+                path: "/app/../etc".to_string(),
+            },
+        );
+        assert_eq!(
+            err.to_string(),
+            r#"instruction 1: WORKDIR "/app/../etc" is likely a path mistake"#
+        );
+
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .push(WORKDIR::from("/app "));
+        assert!(dockerfile.validate().is_err());
+
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from("alpine"))
+            .push(WORKDIR::from("C:\\app"));
+        assert!(dockerfile.validate().is_err());
+
+        let dockerfile = Dockerfile::default()
+            .push(FROM::from(
+                "mcr.microsoft.com/windows/servercore:ltsc2022 AS base",
+            ))
+            .push(WORKDIR::from("C:\\app"))
+            .push(FROM::from("base"))
+            .push(WORKDIR::from("C:\\app\\bin"))
+            .push(FROM::from("alpine"))
+            .push(WORKDIR::from("/app/..data/bin"));
+        assert_eq!(dockerfile.validate(), Ok(()));
+    }
+
+    #[test]
+    fn require_single_stage() {
+        let dockerfile = Dockerfile::default()
+            .push(ARG::from("BASE"))
+            .push(FROM::from("${BASE}"))
+            .push(RUN::from("make"));
+        assert_eq!(dockerfile.require_single_stage(), Ok(()));
+
+        let dockerfile = dockerfile.push_any("FROM alpine");
+        let err = dockerfile.require_single_stage().unwrap_err();
+        assert_eq!(err, ValidationError::MultipleStages { stages: 2 });
+        assert_eq!(
+            err.to_string(),
+            "the Dockerfile has 2 build stages, only a single stage is supported"
+        );
+
+        assert_eq!(
+            Dockerfile::default().require_single_stage(),
+            Err(ValidationError::MissingFrom)
+        );
+    }
+}
@@ -0,0 +1,66 @@
+//! Checks generated Dockerfiles with `docker build --check`
+//!
+//! Run with `cargo test --features docker-tests -- --ignored`, tests are skipped if Docker
+//! isn't available.
+
+#![cfg(feature = "docker-tests")]
+
+mod harness;
+
+use dockerfile_builder::instruction::{EXPOSE, FROM, WORKDIR};
+use dockerfile_builder::instruction_builder::{
+    CmdExecBuilder, CopyBuilder, EnvBuilder, Protocol, RunBuilder,
+};
+use dockerfile_builder::Dockerfile;
+
+use harness::{docker_available, docker_check};
+
+#[test]
+#[ignore = "requires docker"]
+fn multi_stage() {
+    if !docker_available() {
+        eprintln!("docker is not available, skipping");
+        return;
+    }
+
+    let dockerfile = Dockerfile::default()
+        .syntax("docker/dockerfile:1")
+        .comment("generated by dockerfile_builder")
+        .stage("builder", "rust:1.70", |stage| {
+            stage.push(WORKDIR::from("/app")).push(
+                RunBuilder::builder()
+                    .command("cargo new app")
+                    .command("cargo build --release")
+                    .build()
+                    .unwrap(),
+            )
+        })
+        .push(FROM::from("debian:bookworm-slim"))
+        .push(
+            EnvBuilder::builder()
+                .env("RUST_LOG", "info")
+                .env("GREETING", "hello world")
+                .build()
+                .unwrap(),
+        )
+        .push(
+            CopyBuilder::builder()
+                .from("builder")
+                .src("/app/target/release/app")
+                .dest("/usr/local/bin/app")
+                .build()
+                .unwrap(),
+        )
+        .push(EXPOSE::from(format!("8080/{}", Protocol::Tcp)))
+        .push(
+            CmdExecBuilder::builder()
+                .executable("app")
+                .param("--port=8080")
+                .build()
+                .unwrap(),
+        );
+
+    if let Err(output) = docker_check("multi_stage", &dockerfile) {
+        panic!("docker build --check failed:\n{}\n{}", dockerfile, output);
+    }
+}
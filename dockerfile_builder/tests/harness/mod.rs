@@ -0,0 +1,52 @@
+//! Harness checking generated Dockerfiles with the Docker parser
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use dockerfile_builder::Dockerfile;
+
+/// Returns `true` if the `docker` CLI is installed and supports `docker build --check`
+pub fn docker_available() -> bool {
+    Command::new("docker")
+        .args(["build", "--check", "--help"])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Writes the Dockerfile to a temporary build context and runs `docker build --check` on it
+///
+/// Returns the output of `docker` if the check fails.
+pub fn docker_check(name: &str, dockerfile: &Dockerfile) -> Result<(), String> {
+    let context = context_dir(name);
+    fs::create_dir_all(&context).map_err(|e| e.to_string())?;
+    let path = context.join("Dockerfile");
+    dockerfile.write_to_file(&path).map_err(|e| e.to_string())?;
+
+    let output = Command::new("docker")
+        .args(["build", "--check", "-f"])
+        .arg(&path)
+        .arg(&context)
+        .output()
+        .map_err(|e| e.to_string());
+    let _ = fs::remove_dir_all(&context);
+
+    let output = output?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ))
+    }
+}
+
+fn context_dir(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "dockerfile_builder-{}-{}",
+        name,
+        std::process::id()
+    ))
+}
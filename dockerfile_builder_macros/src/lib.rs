@@ -14,27 +14,41 @@ pub fn instruction_init(input: TokenStream) -> TokenStream {
         _ => return utils::make_err(&input.ident, "Expected Enum").into(),
     };
 
+    // Raw variants wrap a String instead of a generated instruction struct
+    let is_raw = |v: &&syn::Variant| v.ident == "ANY" || v.ident == "COMMENT";
+
     let impl_display = variants.iter().map(|v| {
         let variant = &v.ident;
-        quote! {
-            #instruction::#variant(ins) => write!(f, "{}", ins),
+        if variant == "COMMENT" {
+            quote! {
+                #instruction::#variant(comment) => {
+                    let lines = comment
+                        .split('\n')
+                        .map(|line| format!("# {}", line).trim_end().to_string())
+                        .collect::<Vec<_>>();
+                    write!(f, "{}", lines.join("\n"))
+                }
+            }
+        } else {
+            quote! {
+                #instruction::#variant(ins) => write!(f, "{}", ins),
+            }
         }
     });
 
-    let impl_convert_from_for_instruction =
-        variants.iter().filter(|v| &v.ident != "ANY").map(|v| {
-            let variant = &v.ident;
-            quote! {
-                    impl std::convert::From<#variant> for #instruction {
-                        fn from(instruction: #variant) -> Self {
-                            Instruction::#variant(instruction)
-                        }
+    let impl_convert_from_for_instruction = variants.iter().filter(|v| !is_raw(v)).map(|v| {
+        let variant = &v.ident;
+        quote! {
+                impl std::convert::From<#variant> for #instruction {
+                    fn from(instruction: #variant) -> Self {
+                        Instruction::#variant(instruction)
                     }
-            }
-        });
+                }
+        }
+    });
 
     let variant_init = variants.iter()
-        .filter(|v| &v.ident != "ANY")
+        .filter(|v| !is_raw(v))
         .map(|v| {
             let variant = &v.ident;
             let variant_lower = &variant.to_string().to_lowercase();
@@ -57,14 +71,22 @@ let {} = {}::from("some instruction value");
                 ///
                 #[doc = #doc_link_reference]
                 #[derive(Debug, Clone, Eq, PartialEq)]
+                #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
                 pub struct #variant {
                     pub value: String,
+                    /// Comment rendered on the lines before the instruction, ignored by
+                    /// [`Instruction::semantic_eq`](crate::instruction::Instruction::semantic_eq)
+                    #[cfg_attr(
+                        feature = "serde",
+                        serde(default, skip_serializing_if = "Option::is_none")
+                    )]
+                    pub comment: Option<String>,
                 }
             }
         }
     );
 
-    let impl_convert_from_for_variant = variants.iter().filter(|v| &v.ident != "ANY").map(|v| {
+    let impl_convert_from_for_variant = variants.iter().filter(|v| !is_raw(v)).map(|v| {
         let variant = &v.ident;
         let gen_doc = format!("Construct a new {} instruction from raw string", variant);
         quote! {
@@ -73,25 +95,107 @@ let {} = {}::from("some instruction value");
                 fn from(value: T) -> Self {
                     #variant {
                         value: value.into(),
+                        comment: None,
                     }
                 }
             }
         }
     });
 
-    let impl_display_for_variant = variants.iter().filter(|v| &v.ident != "ANY").map(|v| {
+    let impl_display_for_variant = variants.iter().filter(|v| !is_raw(v)).map(|v| {
         let variant = &v.ident;
         let variant_string = &variant.to_string().to_uppercase();
         quote! {
             impl std::fmt::Display for #variant {
                 fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    if let Some(comment) = &self.comment {
+                        for line in comment.lines() {
+                            writeln!(f, "{}", format!("# {}", line).trim_end())?;
+                        }
+                    }
                     write!(f, "{} {}", #variant_string, self.value)
                 }
             }
         }
     });
 
+    let value_arms = variants.iter().map(|v| {
+        let variant = &v.ident;
+        if is_raw(&v) {
+            quote! { #instruction::#variant(raw) => raw, }
+        } else {
+            quote! { #instruction::#variant(ins) => &ins.value, }
+        }
+    });
+    let value_mut_arms = variants.iter().map(|v| {
+        let variant = &v.ident;
+        if is_raw(&v) {
+            quote! { #instruction::#variant(raw) => raw, }
+        } else {
+            quote! { #instruction::#variant(ins) => &mut ins.value, }
+        }
+    });
+
+    let keyword_arms = variants.iter().map(|v| {
+        let variant = &v.ident;
+        let keyword = variant.to_string();
+        if is_raw(&v) {
+            quote! { #instruction::#variant(_) => None, }
+        } else {
+            quote! { #instruction::#variant(_) => Some(#keyword), }
+        }
+    });
+
+    let comment_mut_arms = variants.iter().map(|v| {
+        let variant = &v.ident;
+        if is_raw(&v) {
+            quote! { #instruction::#variant(_) => None, }
+        } else {
+            quote! { #instruction::#variant(ins) => Some(&mut ins.comment), }
+        }
+    });
+
     quote! {
+        impl #instruction {
+            /// Returns the keyword of the instruction, e.g. `"RUN"`, or `None` for `ANY` and
+            /// `COMMENT`
+            ///
+            /// ```
+            /// # use dockerfile_builder::instruction::{Instruction, RUN};
+            /// assert_eq!(Instruction::from(RUN::from("echo hi")).keyword(), Some("RUN"));
+            /// assert_eq!(Instruction::from("# comment").keyword(), None);
+            /// ```
+            pub fn keyword(&self) -> Option<&'static str> {
+                match self {
+                    #(#keyword_arms)*
+                }
+            }
+
+            /// Returns the instruction value without the keyword, or the raw string for `ANY` and
+            /// `COMMENT`
+            pub(crate) fn value(&self) -> &str {
+                match self {
+                    #(#value_arms)*
+                }
+            }
+
+            /// Returns a mutable reference to the instruction value, or the raw string for `ANY`
+            /// and `COMMENT`
+            pub(crate) fn value_mut(&mut self) -> &mut String {
+                match self {
+                    #(#value_mut_arms)*
+                }
+            }
+
+            /// Returns a mutable reference to the comment of the instruction, or `None` for `ANY`
+            /// and `COMMENT`
+            pub(crate) fn comment_mut(&mut self) -> Option<&mut Option<String>> {
+                match self {
+                    #(#comment_mut_arms)*
+                }
+            }
+        }
+
         impl std::fmt::Display for #instruction {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
                 match self {
@@ -131,6 +235,15 @@ pub fn instruction_builder(input: TokenStream) -> TokenStream {
         _ => return utils::make_err(struct_ident, "Expected Struct with named fields").into(),
     };
 
+    let field_attrs = match fields
+        .iter()
+        .map(utils::get_field_attr)
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(field_attrs) => field_attrs,
+        Err(e) => return e.into(),
+    };
+
     let builder_empty = fields.iter().map(|f| {
         let name = &f.ident;
         quote! { #name: std::option::Option::None, }
@@ -175,6 +288,18 @@ pub fn instruction_builder(input: TokenStream) -> TokenStream {
             };
         }
 
+        // Custom set method for Instruction or Option<Instruction>
+        // These methods accept T where T: Into<Instruction>, like `Dockerfile::push`.
+        let inner_ty = utils::inner_type("Option", original_ty).unwrap_or(original_ty);
+        if utils::is_type("Instruction", inner_ty) {
+            return quote! {
+                pub fn #name<T: Into<Instruction>>(&mut self, #name: T) -> &mut Self {
+                    self.#name = Some(#name.into());
+                    self
+                }
+            };
+        }
+
         // Defaut set method.
         // If original type is Option<inner> => set type is inner
         // Else set type is original type
@@ -187,26 +312,8 @@ pub fn instruction_builder(input: TokenStream) -> TokenStream {
         }
     });
 
-    let builder_set_each_method = fields.iter().map(|f| {
-        if f.attrs.is_empty() {
-            return None;
-        }
-
-        if f.attrs.len() != 1 {
-            return utils::make_err(&f.ident, utils::EXPECT_EACH_ATTR_TEMPLATE).into();
-        }
-
-        let each_ident_result = if let Some(field_ident) = &f.ident {
-            utils::get_each_attr(&f.attrs, field_ident)
-        } else {
-            return utils::make_err(&f.ident, "Expect field ident").into();
-        };
-
-        let each_ident = match each_ident_result {
-            Ok(i) => i,
-            Err(e) => return e.into(),
-        };
-
+    let builder_set_each_method = fields.iter().zip(&field_attrs).map(|(f, field_attr)| {
+        let each_ident = field_attr.each.as_ref()?;
         let name = &f.ident;
         let original_ty = &f.ty;
 
@@ -229,7 +336,9 @@ pub fn instruction_builder(input: TokenStream) -> TokenStream {
             });
         }
 
-        let set_ty = if let Some(inner_ty) = utils::inner_type("Vec", original_ty) {
+        // Custom set each method for Vec<T> or Option<Vec<T>>
+        let vec_ty = utils::inner_type("Option", original_ty).unwrap_or(original_ty);
+        let set_ty = if let Some(inner_ty) = utils::inner_type("Vec", vec_ty) {
             inner_ty
         } else {
             return utils::make_err(
@@ -242,7 +351,7 @@ pub fn instruction_builder(input: TokenStream) -> TokenStream {
         Some(quote! {
             pub fn #each_ident(&mut self, #each_ident: #set_ty) -> &mut Self {
                 if self.#name.is_none() {
-                    self.#name = vec![];
+                    self.#name = Some(vec![]);
                 }
                 if let Some(ref mut vector) = self.#name {
                     vector.push(#each_ident);
@@ -254,7 +363,7 @@ pub fn instruction_builder(input: TokenStream) -> TokenStream {
         })
     });
 
-    let builder_check_build_field = fields.iter().map(|f| {
+    let builder_check_build_field = fields.iter().zip(&field_attrs).map(|(f, field_attr)| {
         let name = &f.ident;
         let ty = &f.ty;
 
@@ -262,10 +371,18 @@ pub fn instruction_builder(input: TokenStream) -> TokenStream {
             quote! {
                 #name: self.#name.clone(),
             }
+        } else if let Some(default) = &field_attr.default {
+            // Unset fields with a default value don't fail the build
+            quote! {
+                #name: self.#name.clone().unwrap_or_else(|| (#default).into()),
+            }
         } else {
             quote! {
                 #name: self.#name.clone().ok_or(
-                    eyre::eyre!(concat!(stringify!(#name), " is required for ", stringify!(#struct_ident)))
+                    crate::instruction_builder::BuilderError::MissingField {
+                        field: stringify!(#name),
+                        builder: stringify!(#struct_ident),
+                    }
                 )?,
             }
         }
@@ -283,12 +400,14 @@ pub fn instruction_builder(input: TokenStream) -> TokenStream {
             pub fn builder() -> #builder_ident {
                 #builder_ident {
                     #(#builder_empty)*
+                    comment: std::option::Option::None,
                 }
             }
         }
 
         pub struct #builder_ident {
             #(#builder_field)*
+            comment: std::option::Option<String>,
         }
 
         #[doc = #builder_impl_note]
@@ -298,18 +417,25 @@ pub fn instruction_builder(input: TokenStream) -> TokenStream {
             #(#builder_set_method)*
             #(#builder_set_each_method)*
 
-            fn check_build(&mut self) -> eyre::Result<#struct_ident> {
+            /// Sets a comment rendered on the lines before the instruction
+            pub fn comment<T: Into<String>>(&mut self, comment: T) -> &mut Self {
+                self.comment = Some(comment.into());
+                self
+            }
+
+            fn check_build(&mut self) -> crate::instruction_builder::BuildResult<#struct_ident> {
                 Ok(#struct_ident {
                     #(#builder_check_build_field)*
                 })
             }
 
-            pub fn build(&mut self) -> eyre::Result<#instruction_name> {
+            pub fn build(&mut self) -> crate::instruction_builder::BuildResult<#instruction_name> {
                 let instruction_builder = self.check_build()?;
                 let value = instruction_builder.#value_method()?;
                 Ok(
                     #instruction_name {
                         value,
+                        comment: self.comment.clone(),
                     }
                 )
             }
@@ -71,42 +71,87 @@ const EXPECT_ATTR_TEMPLATE: &str = r#"Expected
     value_method = <method>,
 )]"#;
 
-pub(crate) const EXPECT_EACH_ATTR_TEMPLATE: &str = r#"Expected 
-#[instruction_builder(each = <arg>)]"#;
+pub(crate) struct FieldAttrData {
+    pub(crate) each: Option<syn::Ident>,
+    pub(crate) default: Option<proc_macro2::TokenStream>,
+}
 
-pub(crate) fn get_each_attr(
-    attr: &Vec<syn::Attribute>,
-    struct_ident: &syn::Ident,
-) -> eyre::Result<syn::Ident, proc_macro2::TokenStream> {
-    if attr.len() != 1 {
-        return Err(make_err(struct_ident, EXPECT_EACH_ATTR_TEMPLATE));
-    }
-    if let syn::Meta::List(ref metalist) = &attr[attr.len() - 1].meta {
-        let tokenstream = &mut metalist.tokens.clone().into_iter();
+const EXPECT_FIELD_ATTR_TEMPLATE: &str = r#"Expected 
+#[instruction_builder(
+    [each = <arg>,]
+    [default = <value>,]
+)]"#;
 
-        verify_attr_ident(
+pub(crate) fn get_field_attr(
+    field: &syn::Field,
+) -> Result<FieldAttrData, proc_macro2::TokenStream> {
+    let mut field_attr = FieldAttrData {
+        each: None,
+        default: None,
+    };
+    let attr = match field.attrs.as_slice() {
+        [] => return Ok(field_attr),
+        [attr] => attr,
+        _ => return Err(make_err(field, EXPECT_FIELD_ATTR_TEMPLATE)),
+    };
+    let metalist = match &attr.meta {
+        syn::Meta::List(metalist) => metalist,
+        _ => return Err(make_err(field, EXPECT_FIELD_ATTR_TEMPLATE)),
+    };
+
+    let tokenstream = &mut metalist.tokens.clone().into_iter().peekable();
+    while let Some(token) = tokenstream.next() {
+        let key = match token {
+            TokenTree::Ident(ref i) => i.to_string(),
+            _ => return Err(make_err(metalist, EXPECT_FIELD_ATTR_TEMPLATE)),
+        };
+        verify_attr_punct(
             tokenstream.next(),
-            "each",
+            '=',
             metalist,
-            EXPECT_EACH_ATTR_TEMPLATE,
+            EXPECT_FIELD_ATTR_TEMPLATE,
         )?;
-        verify_attr_punct(tokenstream.next(), '=', metalist, EXPECT_EACH_ATTR_TEMPLATE)?;
 
-        let each_ident = match tokenstream.next() {
-            Some(TokenTree::Ident(ref i)) => i.clone(),
-            _ => return Err(make_err(metalist, EXPECT_EACH_ATTR_TEMPLATE)),
-        };
+        match key.as_str() {
+            "each" if field_attr.each.is_none() => match tokenstream.next() {
+                Some(TokenTree::Ident(ref i)) => field_attr.each = Some(i.clone()),
+                _ => return Err(make_err(metalist, EXPECT_FIELD_ATTR_TEMPLATE)),
+            },
+            "default" if field_attr.default.is_none() => {
+                if is_type("Option", &field.ty) {
+                    return Err(make_err(
+                        field,
+                        r#"Fields must not have Option type to use the "default" attribute"#,
+                    ));
+                }
+                // The value is any expression, up to the next `,`
+                let mut value = proc_macro2::TokenStream::new();
+                while let Some(token) = tokenstream
+                    .next_if(|t| !matches!(t, TokenTree::Punct(ref p) if p.as_char() == ','))
+                {
+                    value.extend([token]);
+                }
+                if value.is_empty() {
+                    return Err(make_err(metalist, EXPECT_FIELD_ATTR_TEMPLATE));
+                }
+                field_attr.default = Some(value);
+            }
+            _ => return Err(make_err(metalist, EXPECT_FIELD_ATTR_TEMPLATE)),
+        }
 
-        Ok(each_ident)
-    } else {
-        Err(make_err(struct_ident, EXPECT_EACH_ATTR_TEMPLATE))
+        match tokenstream.next() {
+            None => break,
+            Some(TokenTree::Punct(ref p)) if p.as_char() == ',' => {}
+            _ => return Err(make_err(metalist, EXPECT_FIELD_ATTR_TEMPLATE)),
+        }
     }
+    Ok(field_attr)
 }
 
 pub(crate) fn get_attr(
-    attr: &Vec<syn::Attribute>,
+    attr: &[syn::Attribute],
     struct_ident: &syn::Ident,
-) -> eyre::Result<AttrData, proc_macro2::TokenStream> {
+) -> Result<AttrData, proc_macro2::TokenStream> {
     if attr.is_empty() {
         return Err(make_err(struct_ident, EXPECT_ATTR_TEMPLATE));
     }
@@ -156,7 +201,7 @@ pub(crate) fn verify_attr_ident<T: quote::ToTokens>(
     expected_ident: &str,
     span: T,
     err_msg: &str,
-) -> eyre::Result<(), proc_macro2::TokenStream> {
+) -> Result<(), proc_macro2::TokenStream> {
     match token {
         Some(TokenTree::Ident(ref i)) => {
             if i != expected_ident {
@@ -173,7 +218,7 @@ pub(crate) fn verify_attr_punct<T: quote::ToTokens>(
     expected_punct: char,
     span: T,
     err_msg: &str,
-) -> eyre::Result<(), proc_macro2::TokenStream> {
+) -> Result<(), proc_macro2::TokenStream> {
     match token {
         Some(TokenTree::Punct(ref p)) => {
             if p.as_char() != expected_punct {